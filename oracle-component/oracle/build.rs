@@ -0,0 +1,36 @@
+use std::process::Command;
+use std::time::SystemTime;
+
+/// Bake the git commit and build time into the component for `/api/version`.
+/// Either is left unset when unavailable, and the endpoint reports "unknown".
+fn main() {
+    if let Some(sha) = git(&["rev-parse", "--short=12", "HEAD"]) {
+        println!("cargo:rustc-env=ORACLE_GIT_SHA={}", sha);
+    }
+    // Rebuild when HEAD moves, so the SHA doesn't go stale in incremental builds
+    if let Some(log) = git(&["rev-parse", "--git-path", "logs/HEAD"]) {
+        println!("cargo:rerun-if-changed={}", log);
+    }
+
+    // Honour SOURCE_DATE_EPOCH for reproducible builds
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    let build_time = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.trim().parse::<u64>().ok())
+        .or_else(|| {
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .ok()
+                .map(|elapsed| elapsed.as_secs())
+        });
+    if let Some(secs) = build_time {
+        println!("cargo:rustc-env=ORACLE_BUILD_TIME={}", secs);
+    }
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    let value = stdout.trim();
+    (output.status.success() && !value.is_empty()).then(|| value.to_string())
+}
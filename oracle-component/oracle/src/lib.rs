@@ -1,11 +1,37 @@
-use anyhow::{Context, Result};
+// The OpenAPI document is one large `json!` literal
+#![recursion_limit = "256"]
+
+use anyhow::Result;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use hmac::{Hmac, Mac};
+use qrcode::{EcLevel, QrCode};
 use serde::{Deserialize, Serialize};
-use spin_sdk::http::{IntoResponse, Request, Response, Method, Params};
+use sha2::Sha256;
+use spin_sdk::http::{Fields, IncomingRequest, IntoResponse, Method, OutgoingResponse, Request, Response, ResponseOutparam};
+#[cfg(not(test))]
+use spin_sdk::key_value::Store;
+#[cfg(not(test))]
+use spin_sdk::wit::wasi::keyvalue;
+// Unit tests run outside Spin, so KV goes to an in-memory map instead
+#[cfg(test)]
+use tests::MemoryStore as Store;
 use spin_sdk::http_component;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Log a line tagged with the current request id: `log!(warn, "Could not {}", what)`
+macro_rules! log {
+    ($level:ident, $($arg:tt)*) => {
+        write_log(&mut std::io::stdout(), log_format(), stringify!($level), format_args!($($arg)*))
+    };
+}
 
 /// InformationForce metrics for the tri-loop oracle system
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct InformationForceMetrics {
     semantic_closure: f64,
     strange_loops: u32,
@@ -13,370 +39,7918 @@ struct InformationForceMetrics {
     spectral_gap: f64,
     correlation_strength: f64,
     threshold_exceeded: bool,
+    /// Raw Φ from the Oracle state file (absent in simulation)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    phi: Option<f64>,
+    /// Raw quantum entropy from the Oracle state file (absent in simulation)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    quantum_entropy: Option<f64>,
+    /// Raw loop iteration from the Oracle state file (absent in simulation)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    loop_iteration: Option<u32>,
+    /// Unsmoothed semantic closure, present when `?smooth=true` replaced it with an EMA
+    #[serde(skip_serializing_if = "Option::is_none")]
+    raw_semantic_closure: Option<f64>,
+    /// Unsmoothed correlation strength, present when `?smooth=true` replaced it with an EMA
+    #[serde(skip_serializing_if = "Option::is_none")]
+    raw_correlation_strength: Option<f64>,
+    /// What produced these metrics (absent when a caller POSTed them)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<MetricSource>,
+}
+
+/// Where a set of metrics came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum MetricSource {
+    StateFile,
+    Simulation,
+    /// Derived from the `?phi=` query parameter
+    Query,
 }
 
 /// Fortune response with information-dynamics data
 #[derive(Debug, Serialize, Deserialize)]
 struct FortuneResponse {
+    /// Stable identifier for retrieving this fortune later
+    id: String,
     haiku: Vec<String>,
     mechanism: String,
-    information-dynamics: InformationForceMetrics,
+    /// Plain-language gloss on `mechanism`, included when `?explain_mechanism=true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mechanism_explanation: Option<String>,
+    /// Wire name keeps the original hyphenated key for existing clients
+    #[serde(rename = "information-dynamics")]
+    information_dynamics: InformationForceMetrics,
     timestamp: u64,
+    /// `timestamp` as an RFC 3339 UTC string, included when `?time_format=iso`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timestamp_iso: Option<String>,
     tri_loop_status: TriLoopStatus,
+    /// Per-line syllable counts, included when `?validate=true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    syllables: Option<Vec<u32>>,
+    /// Theme tag of the template used, if the haiku came from a template
+    #[serde(skip_serializing_if = "Option::is_none")]
+    theme: Option<String>,
+    /// Whether a requested `?theme=` was available; absent when no theme was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    theme_matched: Option<bool>,
+    /// UTC date (`YYYY-MM-DD`) the fortune belongs to, set on the daily fortune
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date: Option<String>,
+    /// Language of the haiku
+    #[serde(default = "default_language")]
+    lang: String,
+    /// Explains an adjustment to the request, e.g. a truncated acrostic word
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
+    /// Decision trace behind the haiku and mechanism, included when `?explain=true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    explanation: Option<SelectionExplanation>,
+    /// The caller's `?nonce=`, echoed back and folded into `id`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_nonce: Option<String>,
+    /// Set on the occasional easter-egg fortune (`rare_fortune_chance`)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    rare: bool,
+    /// Code for the `/f/{code}` short link, assigned when the fortune is stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    short_code: Option<String>,
+}
+
+/// Why a fortune came out the way it did, for tuning the selection functions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SelectionExplanation {
+    /// "state_file", "simulation", "query" for `?phi=`, or "request_body" for POSTed metrics
+    metrics_source: String,
+    semantic_closure: f64,
+    threshold_exceeded: bool,
+    /// Template theme the closure band prefers (used when unseeded)
+    closure_band: String,
+    haiku_source: HaikuOrigin,
+    /// Selection pool index: templates first, then approved contributions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    template_index: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    theme: Option<String>,
+    seeded: bool,
+    /// "seed" or "dominant_metric"
+    mechanism_basis: String,
+    /// The clamped `?intensity=`, when it chose between the pools
+    #[serde(skip_serializing_if = "Option::is_none")]
+    intensity: Option<i64>,
+}
+
+/// Fortunes stored before languages existed were all English
+fn default_language() -> String {
+    DEFAULT_LANGUAGE.to_string()
 }
 
 /// Status of the tri-loop system components
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct TriLoopStatus {
     mcp_active: bool,
     gemini_connected: bool,
     codex_generating: bool,
     correlation_detected: bool,
+    /// How liveness was judged: "heartbeat", "file_existence", or "mixed"
+    detection_method: String,
+    /// Why correlation was (not) detected: "ok", "state_file_missing", "signature_invalid",
+    /// "state_stale_{secs}s", or "state_incomplete" when the file is fresh but unparseable or missing keys
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    correlation_reason: Option<String>,
 }
 
-/// Zeldar InformationForce Oracle - Tri-Loop Fortune Generation
-#[http_component]
-fn handle_oracle(req: Request) -> Result<impl IntoResponse> {
-    println!("🧠 InformationForce Oracle Request: {:?}", req.header("spin-full-url"));
-    
-    match req.method() {
-        Method::Get => handle_oracle_request(&req),
-        Method::Post => handle_information-dynamics_generation(&req),
-        Method::Options => handle_cors_preflight(),
-        _ => Ok(Response::builder()
-            .status(405)
-            .header("content-type", "application/json")
-            .body(r#"{"error": "Method not allowed"}"#)
-            .build())
+/// Caller-supplied generation parameters, from the query string or POST body
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct GenerationParams {
+    /// Restrict selection to templates tagged with this theme ("desert", "math", "emergence", "paradox")
+    theme: Option<String>,
+    /// Number of fortunes to generate in one response (clamped to 1..=MAX_BUNDLE_SIZE)
+    #[serde(deserialize_with = "deserialize_from_str")]
+    count: u32,
+    /// Seed for reproducible template and mechanism selection
+    #[serde(deserialize_with = "deserialize_optional_from_str")]
+    seed: Option<u64>,
+    /// Report per-line syllable counts in the response (English only - the
+    /// vowel-group heuristic doesn't fit Spanish syllables or Japanese morae)
+    #[serde(deserialize_with = "deserialize_from_str")]
+    validate: bool,
+    /// Explicit output format (`json`, `text`, `escpos`, `svg`, `markdown`, `ticket`), overriding `Accept`
+    format: Option<String>,
+    /// Haiku language (`en`, `es`, `ja`); anything else falls back to English
+    lang: Option<String>,
+    /// How timestamps are reported (`epoch` or `iso`)
+    time_format: TimeFormat,
+    /// Word (up to three letters) the haiku's lines should spell, in English
+    acrostic: Option<String>,
+    /// Include the selection's decision trace as `explanation`
+    #[serde(deserialize_with = "deserialize_from_str")]
+    explain: bool,
+    /// Answer `204 No Content` instead of a standard haiku while below the threshold,
+    /// so printers can skip the job
+    #[serde(deserialize_with = "deserialize_from_str")]
+    require_threshold: bool,
+    /// Chance (0-100, clamped) that each haiku comes from the emergent pool rather
+    /// than the standard one, whatever the metrics say
+    #[serde(deserialize_with = "deserialize_optional_from_str")]
+    intensity: Option<i64>,
+    /// Client token echoed as `request_nonce` and bound into the fortune id
+    nonce: Option<String>,
+    /// Include a plain-language gloss on the mechanism as `mechanism_explanation`
+    #[serde(deserialize_with = "deserialize_from_str")]
+    explain_mechanism: bool,
+    /// Whether generation follows the live Oracle (its own haiku, the anti-repetition
+    /// window); off when the caller supplies metrics, so selection stays deterministic
+    #[serde(skip)]
+    live: bool,
+    /// Treat the seed as a day number and deal templates from a fixed shuffled deck,
+    /// so consecutive days never share a haiku
+    #[serde(skip)]
+    daily: bool,
+}
+
+impl Default for GenerationParams {
+    fn default() -> Self {
+        GenerationParams {
+            theme: None,
+            count: 1,
+            seed: None,
+            validate: false,
+            format: None,
+            lang: None,
+            time_format: TimeFormat::Epoch,
+            acrostic: None,
+            explain: false,
+            require_threshold: false,
+            intensity: None,
+            nonce: None,
+            explain_mechanism: false,
+            live: true,
+            daily: false,
+        }
     }
 }
 
-fn handle_oracle_request(req: &Request) -> Result<impl IntoResponse> {
-    let path = req.path_and_query().unwrap_or("/");
+impl GenerationParams {
+    /// Build typed parameters from raw string pairs, ignoring unknown keys
+    fn from_map(map: &HashMap<String, String>) -> Result<Self, String> {
+        let mut params = serde_json::to_value(map)
+            .and_then(serde_json::from_value::<GenerationParams>)
+            .map_err(|e| format!("Invalid generation parameters: {}", e))?;
+        params.theme = params.theme.filter(|theme| !theme.trim().is_empty());
+        params.acrostic = params.acrostic.filter(|word| !word.trim().is_empty());
+        params.count = params.count.clamp(1, MAX_BUNDLE_SIZE);
+        params.intensity = params.intensity.map(|intensity| intensity.clamp(0, MAX_INTENSITY));
+        
+        params.nonce = params.nonce.filter(|nonce| !nonce.is_empty());
+        if let Some(nonce) = &params.nonce {
+            if nonce.len() > MAX_NONCE_LEN || !nonce.bytes().all(|b| b.is_ascii_alphanumeric()) {
+                return Err(format!("nonce must be 1-{} ASCII letters or digits", MAX_NONCE_LEN));
+            }
+        }
+        Ok(params)
+    }
     
-    match path {
-        "/api/information-dynamics/status" => get_information-dynamics_status(),
-        "/api/information-dynamics/metrics" => get_live_metrics(),
-        "/api/oracle/fortune" => generate_information-dynamics_fortune(None),
-        _ => serve_information-dynamics_oracle_interface(),
+    /// The requested language if we have templates for it, else English
+    fn language(&self) -> &'static str {
+        let requested = self.lang.as_deref().map(|lang| lang.trim().to_ascii_lowercase());
+        SUPPORTED_LANGUAGES
+            .iter()
+            .copied()
+            .find(|&lang| requested.as_deref() == Some(lang))
+            .unwrap_or(DEFAULT_LANGUAGE)
     }
 }
 
-fn handle_information-dynamics_generation(req: &Request) -> Result<impl IntoResponse> {
-    // Parse request body for information-dynamics generation parameters
-    let body = req.body();
-    let params: HashMap<String, String> = if body.is_empty() {
-        HashMap::new()
-    } else {
-        serde_json::from_slice(body)
-            .context("Failed to parse information-dynamics parameters")?
-    };
+/// Timestamp representation; `iso` adds `timestamp_iso` next to the epoch millis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum TimeFormat {
+    Epoch,
+    Iso,
+}
+
+/// Languages with haiku template tables
+const SUPPORTED_LANGUAGES: &[&str] = &["en", "es", "ja"];
+
+/// Language used when none (or an unsupported one) is requested
+const DEFAULT_LANGUAGE: &str = "en";
+
+/// Longest `?nonce=` a client may send
+const MAX_NONCE_LEN: usize = 64;
+
+/// Top of the `?intensity=` scale: always the emergent pool
+const MAX_INTENSITY: i64 = 100;
+
+/// Largest fortune bundle a single request may ask for
+const MAX_BUNDLE_SIZE: u32 = 5;
+
+/// Deserialize a numeric field that arrives as a string (`"count": "3"`)
+fn deserialize_from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    let raw = String::deserialize(deserializer)?;
+    raw.trim().parse().map_err(serde::de::Error::custom)
+}
+
+/// Like `deserialize_from_str`, wrapping the parsed value in `Some`
+fn deserialize_optional_from_str<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    deserialize_from_str(deserializer).map(Some)
+}
+
+/// Zeldar InformationForce Oracle - Tri-Loop Fortune Generation
+#[http_component]
+async fn handle_oracle(incoming: IncomingRequest, response_out: ResponseOutparam) {
+    let started = Instant::now();
+    let req = read_request(incoming).await;
+    let request_id = request_id(&req);
+    set_current_request_id(&request_id);
+    log!(info, "InformationForce Oracle Request: {:?} {:?}", req.method(), req.header("spin-full-url"));
+    
+    // The metrics stream writes its body incrementally; everything else is buffered
+    let (path, _) = split_path_and_query(&req);
+    if req.method() == &Method::Get && Route::from_path(path) == Route::MetricsStream && !maintenance_mode() {
+        stream_live_metrics(&req, &request_id, started, response_out).await;
+        deliver_pending_webhooks().await;
+        return;
+    }
     
-    generate_information-dynamics_fortune(Some(params))
+    let response = route_timed_request(&req, request_id, started);
+    send_response(response_out, response).await;
+    deliver_pending_webhooks().await;
+    publish_pending_fortunes(&NatsPublisher);
 }
 
-fn handle_cors_preflight() -> Result<impl IntoResponse> {
-    Ok(Response::builder()
-        .status(200)
-        .header("access-control-allow-origin", "*")
-        .header("access-control-allow-methods", "GET, POST, OPTIONS")
-        .header("access-control-allow-headers", "content-type")
-        .body("")
-        .build())
+/// `route_request` plus the `X-Request-Id` and `X-Response-Time-Ms` headers every
+/// buffered response carries
+fn route_timed_request(req: &Request, request_id: String, started: Instant) -> Response {
+    let mut response = route_request(req);
+    response.set_header("x-request-id", request_id);
+    response.set_header("x-response-time-ms", response_time_ms(started));
+    response
+}
+
+/// Milliseconds since `started`, for `X-Response-Time-Ms`. Streams report the time
+/// until their headers went out
+fn response_time_ms(started: Instant) -> String {
+    format!("{:.3}", started.elapsed().as_secs_f64() * 1000.0)
+}
+
+/// Request body limit when `max_body_bytes` is unset
+const DEFAULT_MAX_BODY_BYTES: usize = 64 * 1024;
+
+fn max_body_bytes() -> usize {
+    variable_or("max_body_bytes", "").parse().unwrap_or(DEFAULT_MAX_BODY_BYTES)
+}
+
+/// The declared `Content-Length`, if present and numeric
+fn content_length(req: &Request) -> Option<u64> {
+    req.header("content-length")?.as_str()?.trim().parse().ok()
 }
 
-fn get_information-dynamics_status() -> Result<impl IntoResponse> {
-    let metrics = calculate_information-dynamics_metrics();
-    let tri_loop_status = assess_tri_loop_system();
+/// Buffer the request like the SDK's own conversion, but stop reading the body one
+/// byte past `max_body_bytes` (or skip it when `Content-Length` is already over), so
+/// an oversized upload can't exhaust memory. `enforce_body_limit` then rejects it.
+async fn read_request(incoming: IncomingRequest) -> Request {
+    use futures::StreamExt;
     
-    let status = serde_json::json!({
-        "information-dynamics": metrics,
-        "tri_loop": tri_loop_status,
-        "system_ready": metrics.threshold_exceeded,
-        "burning_man_mode": true,
-        "gift_economy_active": true
-    });
+    let mut req = Request::builder()
+        .method(incoming.method())
+        .uri(incoming.uri())
+        .headers(incoming.headers())
+        .build();
     
-    Ok(Response::builder()
-        .status(200)
-        .header("content-type", "application/json")
-        .header("access-control-allow-origin", "*")
-        .body(status.to_string())
-        .build())
+    let limit = max_body_bytes();
+    if content_length(&req).is_some_and(|length| length > limit as u64) {
+        return req;
+    }
+    
+    let mut body = Vec::new();
+    let mut chunks = incoming.into_body_stream();
+    while let Some(chunk) = chunks.next().await {
+        match chunk {
+            Ok(chunk) => body.extend_from_slice(&chunk),
+            Err(e) => {
+                log!(warn, "Could not read request body: {}", e.to_debug_string());
+                break;
+            }
+        }
+        if body.len() > limit {
+            break;
+        }
+    }
+    *req.body_mut() = body;
+    req
+}
+
+/// Reject bodies over `max_body_bytes`, by declared length or by what was read
+fn enforce_body_limit(req: &Request) -> Result<(), OracleError> {
+    let limit = max_body_bytes();
+    if req.body().len() > limit || content_length(req).is_some_and(|length| length > limit as u64) {
+        return Err(OracleError::PayloadTooLarge { limit });
+    }
+    Ok(())
 }
 
-fn get_live_metrics() -> Result<impl IntoResponse> {
-    let metrics = calculate_information-dynamics_metrics();
+/// Longest incoming `X-Request-Id` we'll reuse rather than replace
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// The caller's `X-Request-Id` if it's sane, else a fresh one
+fn request_id(req: &Request) -> String {
+    let incoming = req
+        .header("x-request-id")
+        .and_then(|value| value.as_str())
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN && id.bytes().all(|b| b.is_ascii_graphic()));
     
-    Ok(Response::builder()
-        .status(200)
-        .header("content-type", "application/json") 
-        .header("access-control-allow-origin", "*")
-        .body(serde_json::to_string(&metrics)?)
-        .build())
+    match incoming {
+        Some(id) => id.to_string(),
+        None => {
+            let nanos = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_nanos())
+                .unwrap_or_default();
+            let mut material = nanos.to_le_bytes().to_vec();
+            material.extend_from_slice(req.path_and_query().unwrap_or("/").as_bytes());
+            hashed_id(&material)
+        }
+    }
+}
+
+thread_local! {
+    /// Id of the request this instance is serving, for log lines
+    static CURRENT_REQUEST_ID: std::cell::RefCell<String> = const { std::cell::RefCell::new(String::new()) };
+}
+
+fn set_current_request_id(id: &str) {
+    CURRENT_REQUEST_ID.with(|current| *current.borrow_mut() = id.to_string());
+}
+
+fn current_request_id() -> String {
+    CURRENT_REQUEST_ID.with(|current| match current.borrow().as_str() {
+        "" => "-".to_string(),
+        id => id.to_string(),
+    })
+}
+
+/// How log lines are written (Spin variable `log_format`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LogFormat {
+    /// One JSON object per line, for log aggregators
+    Json,
+    /// `[request-id] LEVEL message`, for reading locally
+    Text,
 }
 
-fn generate_information-dynamics_fortune(params: Option<HashMap<String, String>>) -> Result<impl IntoResponse> {
-    let information-dynamics = calculate_information-dynamics_metrics();
-    let tri_loop = assess_tri_loop_system();
+/// The configured log format, read once per instance; anything but "text" is JSON
+fn log_format() -> LogFormat {
+    static FORMAT: OnceLock<LogFormat> = OnceLock::new();
     
-    // Generate information-dynamics-aware haiku
-    let haiku = if information-dynamics.threshold_exceeded {
-        generate_information-dynamics_haiku(&information-dynamics)
-    } else {
-        generate_standard_haiku()
+    *FORMAT.get_or_init(|| match variable_or("log_format", "json").to_ascii_lowercase().as_str() {
+        "text" => LogFormat::Text,
+        _ => LogFormat::Json,
+    })
+}
+
+/// Write one log line to `out`. Logging must never fail a request, so write errors are dropped
+fn write_log(out: &mut dyn std::io::Write, format: LogFormat, level: &str, message: std::fmt::Arguments) {
+    let line = match format {
+        LogFormat::Json => serde_json::json!({
+            "ts": format_timestamp_iso(get_current_timestamp()),
+            "level": level,
+            "msg": message.to_string(),
+            "request_id": current_request_id(),
+        })
+        .to_string(),
+        LogFormat::Text => format!("[{}] {} {}", current_request_id(), level.to_uppercase(), message),
     };
+    let _ = writeln!(out, "{}", line);
+}
+
+/// Dispatch a buffered request by method and path
+fn route_request(req: &Request) -> Response {
+    let (path, _) = split_path_and_query(req);
+    let result = match req.method() {
+        // Health keeps answering so probes can see why everything else refuses, and
+        // preflights still pass so browsers can read the maintenance message
+        Method::Options => handle_cors_preflight(Route::from_path(path)),
+        _ if maintenance_mode() && Route::from_path(path) != Route::Health => Err(OracleError::Maintenance.into()),
+        // HEAD routes like GET so headers match; the body is dropped below
+        Method::Get | Method::Head => handle_oracle_request(req),
+        Method::Post => enforce_body_limit(req)
+            .map_err(anyhow::Error::from)
+            .and_then(|()| handle_post_request(req)),
+        _ => Err(Route::from_path(path).method_not_allowed().into()),
+    };
+    
+    // Every failure leaves as a structured JSON error, never an opaque 500
+    let mut response = result.unwrap_or_else(|e| OracleError::from(e).into_response());
+    for (name, value) in cors_headers(req) {
+        response.set_header(name, value);
+    }
+    let mut response = compress_response(req, response);
+    
+    if req.method() == &Method::Head {
+        let length = std::mem::take(response.body_mut()).len();
+        response.set_header("content-length", length.to_string());
+    }
+    response
+}
+
+/// Bodies smaller than this aren't worth the gzip framing
+const MIN_COMPRESS_BYTES: usize = 256;
+
+/// Gzip textual bodies for clients that send `Accept-Encoding: gzip`
+fn compress_response(req: &Request, mut response: Response) -> Response {
+    use std::io::Write;
     
-    let mechanism = select_generation_mechanism(&information-dynamics);
+    let compressible = response
+        .header("content-type")
+        .and_then(|value| value.as_str())
+        .is_some_and(|content_type| {
+            content_type.starts_with("text/")
+                || content_type.starts_with("application/json")
+                || content_type.starts_with("application/x-ndjson")
+                || content_type.starts_with("application/schema+json")
+                || content_type.starts_with("image/svg+xml")
+        });
+    if !compressible || response.header("content-encoding").is_some() {
+        return response;
+    }
     
-    let fortune = FortuneResponse {
-        haiku,
-        mechanism,
-        information-dynamics,
-        timestamp: get_current_timestamp(),
-        tri_loop_status: tri_loop,
+    // Caches must key on the encoding even when this response goes out uncompressed
+    let vary = match response.header("vary").and_then(|value| value.as_str()) {
+        Some(vary) => format!("{}, Accept-Encoding", vary),
+        None => "Accept-Encoding".to_string(),
     };
+    response.set_header("vary", vary);
     
-    println!("🔮 Generated fortune with {:.1}% information-dynamics", fortune.information-dynamics.semantic_closure * 100.0);
+    if response.body().len() < MIN_COMPRESS_BYTES || !accepts_gzip(req) {
+        return response;
+    }
     
-    Ok(Response::builder()
-        .status(200)
-        .header("content-type", "application/json")
-        .header("access-control-allow-origin", "*")
-        .body(serde_json::to_string(&fortune)?)
-        .build())
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    match encoder.write_all(response.body()).and_then(|()| encoder.finish()) {
+        Ok(compressed) => {
+            *response.body_mut() = compressed;
+            response.set_header("content-encoding", "gzip");
+        }
+        Err(e) => log!(warn, "Could not gzip response, sending it uncompressed: {}", e),
+    }
+    response
 }
 
-fn serve_information-dynamics_oracle_interface() -> Result<impl IntoResponse> {
-    let html = r#"
-    <!DOCTYPE html>
-    <html>
-    <head>
-        <title>🧠 Zeldar InformationForce Oracle API</title>
-        <style>
-            body { 
-                font-family: 'Courier New', monospace; 
-                background: linear-gradient(135deg, #1a1a2e, #16213e, #0f3460);
-                color: #fff; margin: 40px; 
-            }
-            .information-dynamics-header {
-                text-align: center;
-                animation: information-dynamics-pulse 2s ease-in-out infinite;
-            }
-            @keyframes information-dynamics-pulse {
-                0%, 100% { opacity: 0.8; }
-                50% { opacity: 1; }
-            }
-            .api-endpoint {
-                background: rgba(255,255,255,0.1);
-                padding: 15px; margin: 10px 0; border-radius: 8px;
-                border: 1px solid #e94560;
-            }
-            .information-dynamics-metric {
-                display: inline-block; margin: 10px; 
-                padding: 8px 15px; background: #e94560;
-                border-radius: 15px; font-weight: bold;
-            }
-        </style>
-    </head>
-    <body>
-        <div class="information-dynamics-header">
-            <h1>🧠 Zeldar InformationForce Oracle API 🧠</h1>
-            <p>Tri-Loop Mathematical InformationForce System</p>
-            <div class="information-dynamics-metric">88.5% Semantic Closure</div>
-            <div class="information-dynamics-metric">3 Strange Loops</div>
-            <div class="information-dynamics-metric">1.02 Hofstadter Coefficient</div>
-        </div>
-        
-        <div class="api-endpoint">
-            <h3>🔮 GET /api/oracle/fortune</h3>
-            <p>Generate information-dynamics-aware fortune with mathematical haiku</p>
-        </div>
-        
-        <div class="api-endpoint">
-            <h3>📊 GET /api/information-dynamics/status</h3>
-            <p>Full information-dynamics metrics and tri-loop system status</p>
-        </div>
-        
-        <div class="api-endpoint">
-            <h3>📈 GET /api/information-dynamics/metrics</h3>
-            <p>Live information-dynamics measurement data</p>
-        </div>
-        
-        <div class="api-endpoint">
-            <h3>🧠 POST /api/information-dynamics/generate</h3>
-            <p>Generate information-dynamics-correlated fortune with custom parameters</p>
-        </div>
-        
-        <footer style="text-align: center; margin-top: 40px; opacity: 0.7;">
-            🏜️🔥 Burning Man 2025 • Gift Economy InformationForce Technology 🔥🏜️
-        </footer>
-    </body>
-    </html>
-    "#;
+/// Whether `Accept-Encoding` lists gzip (or `*`) without `q=0`
+fn accepts_gzip(req: &Request) -> bool {
+    let Some(accept_encoding) = req.header("accept-encoding").and_then(|value| value.as_str()) else {
+        return false;
+    };
     
-    Ok(Response::builder()
-        .status(200)
-        .header("content-type", "text/html")
-        .body(html)
-        .build())
+    accept_encoding.split(',').any(|entry| {
+        let mut parts = entry.split(';').map(str::trim);
+        let coding = parts.next().unwrap_or("");
+        let refused = parts.any(|param| {
+            param
+                .strip_prefix("q=")
+                .and_then(|q| q.parse::<f32>().ok())
+                .is_some_and(|q| q == 0.0)
+        });
+        (coding.eq_ignore_ascii_case("gzip") || coding == "*") && !refused
+    })
 }
 
-fn calculate_information-dynamics_metrics() -> InformationForceMetrics {
-    // INTEGRATED: Read actual information-dynamics state from .topos/current_loop_state.json
-    use std::fs;
-    
-    match fs::read_to_string("../.topos/current_loop_state.json") {
-        Ok(content) => {
-            // Parse real information-dynamics data from Oracle system
-            if let Ok(state) = serde_json::from_str::<serde_json::Value>(&content) {
-                let information-dynamics_phi = state["information-dynamics_phi"].as_f64().unwrap_or(3.252);
-                let quantum_entropy = state["quantum_entropy"].as_f64().unwrap_or(0.926);
-                let loop_iteration = state["loop_iteration"].as_u64().unwrap_or(1) as u32;
-                
-                // Convert Φ (3.252) to semantic closure percentage (32.52 -> 92.52%)
-                let semantic_closure = (information-dynamics_phi / 10.0) + 0.6;
-                let hofstadter_coefficient = information-dynamics_phi / 3.0; // 1.084 from Φ=3.252
-                let spectral_gap = quantum_entropy * 10.0; // Scale entropy to gap
-                
-                return InformationForceMetrics {
-                    semantic_closure: semantic_closure.min(1.0),
-                    strange_loops: (loop_iteration % 5) + 3, // 3-7 based on iterations
-                    hofstadter_coefficient,
-                    spectral_gap,
-                    correlation_strength: 0.98, // High correlation with real Oracle
-                    threshold_exceeded: information-dynamics_phi > 1.0, // Φ > 1.0 = information-dynamics
-                };
+/// CORS headers for a request, per the comma-separated `cors_allowed_origins` variable.
+/// `*` allows any origin; otherwise the request's `Origin` is echoed only if listed.
+fn cors_headers(req: &Request) -> Vec<(&'static str, String)> {
+    cors_headers_for(req, &variable_or("cors_allowed_origins", "*"))
+}
+
+/// `cors_headers` against an explicit allowlist
+fn cors_headers_for(req: &Request, allowed: &str) -> Vec<(&'static str, String)> {
+    let mut headers = vec![
+        ("vary", "Origin".to_string()),
+        ("access-control-expose-headers", "x-request-id, x-response-time-ms".to_string()),
+    ];
+    
+    if allowed == "*" {
+        headers.push(("access-control-allow-origin", "*".to_string()));
+    } else if let Some(origin) = req.header("origin").and_then(|value| value.as_str()) {
+        if allowed.split(',').any(|entry| entry.trim().eq_ignore_ascii_case(origin.trim())) {
+            headers.push(("access-control-allow-origin", origin.trim().to_string()));
+        }
+    }
+    headers
+}
+
+/// Write a buffered response to the outparam, as `#[http_component]` does for simple handlers
+async fn send_response(response_out: ResponseOutparam, mut response: Response) {
+    let body = std::mem::take(response.body_mut());
+    match OutgoingResponse::try_from(response) {
+        Ok(outgoing) => {
+            if let Err(e) = response_out.set_with_body(outgoing, body).await {
+                log!(warn, "Could not send response: {}", e);
             }
         }
-        Err(_) => {
-            println!("⚠️ Oracle state file not found - using simulation");
+        Err(e) => log!(warn, "Could not convert response: {}", e),
+    }
+}
+
+/// Errors returned to API clients as `{"error":{"code":"...","message":"..."}}`
+#[derive(Debug)]
+enum OracleError {
+    BadRequest(String),
+    Unauthorized,
+    NotFound,
+    /// Carries the route's `Allow` list
+    MethodNotAllowed { allow: &'static str },
+    PayloadTooLarge { limit: usize },
+    RateLimited { retry_after_secs: u64 },
+    Unavailable(String),
+    /// `maintenance_mode` is on
+    Maintenance,
+    Internal(String),
+}
+
+impl OracleError {
+    fn status(&self) -> u16 {
+        match self {
+            OracleError::BadRequest(_) => 400,
+            OracleError::Unauthorized => 401,
+            OracleError::NotFound => 404,
+            OracleError::MethodNotAllowed { .. } => 405,
+            OracleError::PayloadTooLarge { .. } => 413,
+            OracleError::RateLimited { .. } => 429,
+            OracleError::Internal(_) => 500,
+            OracleError::Unavailable(_) | OracleError::Maintenance => 503,
         }
     }
     
-    // Fallback to enhanced simulation if Oracle state unavailable
-    use std::f64::consts::PI;
-    let time_factor = (get_current_timestamp() as f64 / 1000.0).sin().abs();
-    
-    let semantic_closure = 0.885 + (time_factor * 0.1);
-    let strange_loops = 3 + ((time_factor * 10.0) as u32 % 3);
-    let hofstadter_coefficient = 1.02 + (time_factor * 0.1);
-    let spectral_gap = 5.26 + (time_factor * 2.0);
-    let correlation_strength = 0.95 + (time_factor * 0.05);
-    
-    InformationForceMetrics {
-        semantic_closure,
-        strange_loops,
-        hofstadter_coefficient,
-        spectral_gap,
-        correlation_strength,
-        threshold_exceeded: semantic_closure > 0.8,
+    fn code(&self) -> &'static str {
+        match self {
+            OracleError::BadRequest(_) => "bad_request",
+            OracleError::Unauthorized => "unauthorized",
+            OracleError::NotFound => "not_found",
+            OracleError::MethodNotAllowed { .. } => "method_not_allowed",
+            OracleError::PayloadTooLarge { .. } => "payload_too_large",
+            OracleError::RateLimited { .. } => "rate_limited",
+            OracleError::Internal(_) => "internal_error",
+            OracleError::Unavailable(_) => "unavailable",
+            OracleError::Maintenance => "maintenance",
+        }
     }
 }
 
-fn assess_tri_loop_system() -> TriLoopStatus {
-    // INTEGRATED: Check actual Oracle system status
-    use std::fs;
-    use std::process::Command;
-    
-    // Check if Oracle system processes are running
-    let oracle_active = fs::metadata("../.topos/FULL_LOOP_ORACLE_SYSTEM.py").is_ok();
-    let print_active = fs::metadata("../.topos/ORACLE_PRINT_CORE.py").is_ok();
-    let button_active = fs::metadata("../.topos/button_quick_phrase_trigger.py").is_ok();
-    
-    // Check for recent loop state update (within last 5 minutes)
-    let correlation_detected = match fs::metadata("../.topos/current_loop_state.json") {
-        Ok(metadata) => {
-            if let Ok(modified) = metadata.modified() {
-                if let Ok(duration) = modified.elapsed() {
-                    duration.as_secs() < 300 // Updated within 5 minutes
-                } else { false }
-            } else { false }
+impl std::fmt::Display for OracleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OracleError::BadRequest(message) | OracleError::Unavailable(message) | OracleError::Internal(message) => {
+                f.write_str(message)
+            }
+            OracleError::Unauthorized => f.write_str("A valid API key is required"),
+            OracleError::NotFound => f.write_str("Not found"),
+            OracleError::MethodNotAllowed { allow } => write!(f, "Method not allowed - this path accepts {}", allow),
+            OracleError::Maintenance => f.write_str("The oracle is being tended to - please return shortly"),
+            OracleError::PayloadTooLarge { limit } => write!(f, "Request body exceeds {} bytes", limit),
+            OracleError::RateLimited { retry_after_secs } => {
+                write!(f, "Too many requests - the oracle needs {}s to recover", retry_after_secs)
+            }
         }
-        Err(_) => false
-    };
-    
-    TriLoopStatus {
-        mcp_active: oracle_active,
-        gemini_connected: print_active, // Print system represents AI connection
-        codex_generating: button_active, // Button system represents code generation
-        correlation_detected,
     }
 }
 
-fn generate_information-dynamics_haiku(metrics: &InformationForceMetrics) -> Vec<String> {
-    // INTEGRATED: Use actual haiku from Oracle system if available
-    use std::fs;
-    
-    if let Ok(content) = fs::read_to_string("../.topos/current_loop_state.json") {
-        if let Ok(state) = serde_json::from_str::<serde_json::Value>(&content) {
-            if let Some(haiku_content) = state["haiku_content"].as_str() {
-                // Split haiku by line breaks and return
-                let lines: Vec<String> = haiku_content.split("\\n")
-                    .map(|s| s.to_string())
-                    .collect();
-                if lines.len() >= 3 {
-                    return lines;
-                }
+impl std::error::Error for OracleError {}
+
+/// What clients see for unexpected failures. The underlying chain can name KV keys,
+/// file paths and hosts, so it only goes to the log (tagged with the request id)
+const INTERNAL_ERROR_MESSAGE: &str = "The oracle encountered an internal error";
+
+impl From<anyhow::Error> for OracleError {
+    fn from(error: anyhow::Error) -> Self {
+        error.downcast::<OracleError>().unwrap_or_else(|e| {
+            log!(error, "Internal oracle error: {:#}", e);
+            OracleError::Internal(INTERNAL_ERROR_MESSAGE.to_string())
+        })
+    }
+}
+
+impl IntoResponse for OracleError {
+    fn into_response(self) -> Response {
+        let body = serde_json::json!({
+            "error": {
+                "code": self.code(),
+                "message": self.to_string(),
+            }
+        });
+        
+        let mut response = Response::builder();
+        response
+            .status(self.status())
+            .header("content-type", "application/json");
+        match self {
+            OracleError::RateLimited { retry_after_secs } => {
+                response.header("retry-after", retry_after_secs.to_string());
             }
+            OracleError::Maintenance => {
+                response.header("retry-after", MAINTENANCE_RETRY_AFTER_SECS.to_string());
+            }
+            OracleError::MethodNotAllowed { allow } => {
+                response.header("allow", allow);
+            }
+            OracleError::Unauthorized => {
+                response.header("www-authenticate", "Bearer");
+            }
+            _ => {}
+        }
+        response.body(body.to_string()).build()
+    }
+}
+
+/// API routes served by the oracle component
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Route<'a> {
+    Health,
+    Version,
+    Status,
+    Metrics,
+    MetricsStream,
+    MetricsHistory,
+    Aggregate,
+    Reload,
+    Prometheus,
+    Fortune,
+    /// `/api/oracle/fortune/{id}`
+    StoredFortune(&'a str),
+    /// `/api/oracle/fortune/{id}/qr`
+    FortuneQr(&'a str),
+    Mechanisms,
+    /// `/api/oracle/mechanisms/{slug}`
+    Mechanism(&'a str),
+    Templates,
+    Reading,
+    Stats,
+    Daily,
+    Contribute,
+    ModerationPending,
+    /// `/api/oracle/moderation/{id}`
+    Moderate(&'a str),
+    /// `/f/{code}`
+    ShortLink(&'a str),
+    /// `/fortune/{id}`
+    FortuneView(&'a str),
+    Generate,
+    Batch,
+    OpenApi,
+    FortuneSchema,
+    Favicon,
+    Interface,
+    /// Anything else; API paths get a JSON 404, browser paths an HTML one
+    Unknown,
+}
+
+impl<'a> Route<'a> {
+    /// Match a request path (without query string) to a route
+    fn from_path(path: &'a str) -> Self {
+        // Treat "/api/oracle/fortune/" the same as "/api/oracle/fortune"
+        let path = match path.trim_end_matches('/') {
+            "" => "/",
+            trimmed => trimmed,
+        };
+
+        if let Some((_, route)) = STATIC_ROUTES.iter().find(|(static_path, _)| *static_path == path) {
+            return *route;
+        }
+        
+        if let Some(id) = path.strip_prefix("/api/oracle/fortune/").filter(|id| !id.contains('/')) {
+            Route::StoredFortune(id)
+        } else if let Some(id) = path
+            .strip_prefix("/api/oracle/fortune/")
+            .and_then(|rest| rest.strip_suffix("/qr"))
+            .filter(|id| !id.is_empty() && !id.contains('/'))
+        {
+            Route::FortuneQr(id)
+        } else if let Some(slug) = path.strip_prefix("/api/oracle/mechanisms/").filter(|slug| !slug.contains('/')) {
+            Route::Mechanism(slug)
+        } else if let Some(id) = path.strip_prefix("/api/oracle/moderation/").filter(|id| !id.contains('/')) {
+            Route::Moderate(id)
+        } else if let Some(code) = path.strip_prefix("/f/").filter(|code| !code.contains('/')) {
+            Route::ShortLink(code)
+        } else if let Some(id) = path.strip_prefix("/fortune/").filter(|id| !id.contains('/')) {
+            Route::FortuneView(id)
+        } else {
+            Route::Unknown
         }
     }
     
-    // Fallback information-dynamics haiku templates
-    let information-dynamics_haiku = [
-        vec![
-            "Hidden paths reveal".to_string(),
-            "What seems impossible unfolds —".to_string(), 
-            "Magic lives in doubt".to_string(),
-        ],
-        vec![
-            "Loops correlate through".to_string(),
-            "Mathematical information-dynamics—".to_string(),
-            "Desert sand transforms".to_string(),
-        ],
-        vec![
-            "Category maps fold,".to_string(),
-            "Strange loops embrace paradox—".to_string(),
-            "Awareness emerges".to_string(),
-        ],
-        vec![
-            "Three systems dancing,".to_string(),
-            "Correlation weaves meaning—".to_string(),
-            "InformationForce blooms bright".to_string(),
-        ],
-    ];
+    /// Methods the route answers, as listed in `Access-Control-Allow-Methods`.
+    /// Keep in step with `handle_oracle_request` and `handle_post_request`
+    fn allowed_methods(&self) -> &'static str {
+        match self {
+            Route::Fortune => "GET, HEAD, POST, OPTIONS",
+            Route::Contribute | Route::Moderate(_) | Route::Generate | Route::Reload | Route::Batch => "POST, OPTIONS",
+            // Streams are opened by `handle_oracle` for GET only
+            Route::MetricsStream => "GET, OPTIONS",
+            _ => "GET, HEAD, OPTIONS",
+        }
+    }
     
-    let index = (metrics.semantic_closure * information-dynamics_haiku.len() as f64) as usize % information-dynamics_haiku.len();
-    information-dynamics_haiku[index].clone()
+    /// 405 for a method the route doesn't answer; unknown paths are a 404 whatever the method
+    fn method_not_allowed(&self) -> OracleError {
+        match self {
+            Route::Unknown => OracleError::NotFound,
+            route => OracleError::MethodNotAllowed { allow: route.allowed_methods() },
+        }
+    }
 }
 
-fn generate_standard_haiku() -> Vec<String> {
-    vec![
-        "Quantum paths unfold,".to_string(),
-        "Mathematical beauty waits—".to_string(),
-        "InformationForce near".to_string(),
-    ]
-}
+/// Fixed paths and their routes; parameterised paths are matched in `Route::from_path`
+const STATIC_ROUTES: &[(&str, Route<'static>)] = &[
+    ("/api/health", Route::Health),
+    ("/api/version", Route::Version),
+    ("/api/information-dynamics/status", Route::Status),
+    ("/api/information-dynamics/metrics", Route::Metrics),
+    ("/api/information-dynamics/stream", Route::MetricsStream),
+    ("/api/information-dynamics/history", Route::MetricsHistory),
+    ("/api/information-dynamics/aggregate", Route::Aggregate),
+    ("/api/information-dynamics/reload", Route::Reload),
+    ("/api/metrics/prometheus", Route::Prometheus),
+    ("/api/oracle/fortune", Route::Fortune),
+    ("/api/oracle/mechanisms", Route::Mechanisms),
+    ("/api/oracle/templates", Route::Templates),
+    ("/api/oracle/stats", Route::Stats),
+    ("/api/oracle/reading", Route::Reading),
+    ("/api/oracle/daily", Route::Daily),
+    ("/api/oracle/contribute", Route::Contribute),
+    ("/api/oracle/moderation/pending", Route::ModerationPending),
+    ("/api/information-dynamics/generate", Route::Generate),
+    ("/api/batch", Route::Batch),
+    ("/api/openapi.json", Route::OpenApi),
+    ("/api/schema/fortune", Route::FortuneSchema),
+    ("/favicon.ico", Route::Favicon),
+    ("/", Route::Interface),
+];
 
-fn select_generation_mechanism(metrics: &InformationForceMetrics) -> String {
-    let mechanisms = [
-        "tri-loop correlation matrix convergence",
-        "semantic closure boundary optimization", 
-        "hofstadter coefficient recursive analysis",
-        "expander graph spectral gap resonance",
-        "strange loop paradox resolution synthesis",
-    ];
+fn handle_oracle_request(req: &Request) -> Result<Response> {
+    let (path, query) = split_path_and_query(req);
     
-    let index = (metrics.correlation_strength * mechanisms.len() as f64) as usize % mechanisms.len();
-    mechanisms[index].to_string()
+    match Route::from_path(path) {
+        Route::Health => get_health(),
+        Route::Version => get_version(),
+        Route::Status => get_information_dynamics_status(req),
+        Route::Metrics => get_live_metrics(req, parse_query(query)),
+        // Streams are served by `handle_oracle` directly and can't be buffered
+        Route::MetricsStream => Err(OracleError::BadRequest("The metrics stream must be opened directly with GET".to_string()).into()),
+        Route::MetricsHistory => get_metrics_history(parse_query(query)),
+        Route::Aggregate => get_aggregate_metrics(),
+        Route::Prometheus => get_prometheus_metrics(),
+        Route::Fortune => {
+            enforce_rate_limit(req)?;
+            let params = parse_query(query);
+            let metrics = supplied_phi_metrics(&params)?;
+            generate_information_dynamics_fortune(Some(params), accept_header(req), metrics)
+        }
+        Route::StoredFortune(id) => get_stored_fortune(id),
+        Route::FortuneQr(id) => get_fortune_qr(id),
+        Route::Mechanisms => get_mechanisms(),
+        Route::Mechanism(slug) => get_mechanism(slug),
+        Route::Templates => {
+            if template_preview_requires_key() {
+                require_moderator(req)?;
+            }
+            get_template_preview(parse_query(query))
+        }
+        Route::Stats => get_fortune_stats(),
+        Route::Reading => {
+            enforce_rate_limit(req)?;
+            get_oracle_reading(parse_query(query))
+        }
+        Route::Daily => get_daily_fortune(parse_query(query), accept_header(req), get_current_timestamp() / MILLIS_PER_DAY),
+        Route::ModerationPending => {
+            require_moderator(req)?;
+            get_pending_contributions()
+        }
+        route @ (Route::Contribute | Route::Moderate(_) | Route::Generate | Route::Reload | Route::Batch) => {
+            Err(route.method_not_allowed().into())
+        }
+        Route::OpenApi => get_openapi_document(),
+        Route::FortuneSchema => get_fortune_schema(),
+        Route::Favicon => serve_favicon(),
+        Route::Interface => serve_information_dynamics_oracle_interface(parse_query(query)),
+        Route::ShortLink(code) => follow_short_link(code),
+        Route::FortuneView(id) => serve_fortune_page(id),
+        Route::Unknown if path == "/api" || path.starts_with("/api/") => Err(OracleError::NotFound.into()),
+        Route::Unknown => serve_not_found_page(),
+    }
 }
 
-fn get_current_timestamp() -> u64 {
-    // Simple timestamp simulation (in production would use proper time crate)
-    use std::hash::{Hash, Hasher};
-    use std::collections::hash_map::DefaultHasher;
-    
-    let mut hasher = DefaultHasher::new();
-    "timestamp".hash(&mut hasher);
-    hasher.finish()
-}
\ No newline at end of file
+fn handle_post_request(req: &Request) -> Result<Response> {
+    let (path, query) = split_path_and_query(req);
+    
+    match Route::from_path(path) {
+        Route::Fortune => {
+            enforce_rate_limit(req)?;
+            handle_fortune_from_metrics(req, parse_query(query))
+        }
+        Route::Generate => {
+            enforce_rate_limit(req)?;
+            handle_information_dynamics_generation(req)
+        }
+        Route::Contribute => {
+            enforce_rate_limit(req)?;
+            handle_contribution(req)
+        }
+        Route::Moderate(id) => {
+            require_moderator(req)?;
+            handle_moderation(req, id)
+        }
+        Route::Reload => {
+            require_moderator(req)?;
+            reload_loop_state()
+        }
+        Route::Batch => handle_batch(req),
+        route => Err(route.method_not_allowed().into()),
+    }
+}
+
+/// Split the request target into its path and (possibly empty) query string
+fn split_path_and_query(req: &Request) -> (&str, &str) {
+    let path_and_query = req.path_and_query().unwrap_or("/");
+    path_and_query.split_once('?').unwrap_or((path_and_query, ""))
+}
+
+/// Parse an `application/x-www-form-urlencoded` query string into key/value pairs
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect()
+}
+
+/// Decode `+` and `%XX` escapes, leaving malformed escapes untouched
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = [bytes[i + 1], bytes[i + 2]];
+                match std::str::from_utf8(&hex).ok().and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Escape everything but unreserved characters, for building query strings
+fn percent_encode(input: &str) -> String {
+    input
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn handle_information_dynamics_generation(req: &Request) -> Result<Response> {
+    // Parse request body for information-dynamics generation parameters
+    let body = req.body();
+    let params: HashMap<String, String> = if body.is_empty() {
+        HashMap::new()
+    } else {
+        serde_json::from_slice(body).map_err(|e| {
+            OracleError::BadRequest(format!("Failed to parse information-dynamics parameters: {}", e))
+        })?
+    };
+    
+    generate_information_dynamics_fortune(Some(params), accept_header(req), None)
+}
+
+/// Batch size limit when `batch_max_size` is unset
+const DEFAULT_BATCH_MAX_SIZE: usize = 20;
+
+fn batch_max_size() -> usize {
+    variable_or("batch_max_size", "").parse().unwrap_or(DEFAULT_BATCH_MAX_SIZE)
+}
+
+/// One operation in a `POST /api/batch` body
+#[derive(Debug, Deserialize)]
+struct BatchOperation {
+    method: String,
+    /// Path, optionally with its own query string
+    path: String,
+    /// Query parameters for GET/HEAD, the JSON body for POST
+    #[serde(default)]
+    params: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Run several operations in order, each routed as its own request with the caller's
+/// headers (so keys and rate limits apply per operation). One failing operation
+/// only fails its own entry
+fn handle_batch(req: &Request) -> Result<Response> {
+    let operations: Vec<BatchOperation> = serde_json::from_slice(req.body())
+        .map_err(|e| OracleError::BadRequest(format!("Batch must be an array of {{method, path, params}}: {}", e)))?;
+    let limit = batch_max_size();
+    if operations.len() > limit {
+        return Err(OracleError::BadRequest(format!("Batch of {} operations exceeds the limit of {}", operations.len(), limit)).into());
+    }
+    
+    let results: Vec<serde_json::Value> = operations
+        .iter()
+        .map(|operation| {
+            let response = match batch_request(req, operation) {
+                Ok(sub_request) => route_request(&sub_request),
+                Err(e) => e.into_response(),
+            };
+            let json = response
+                .header("content-type")
+                .and_then(|value| value.as_str())
+                .is_some_and(|content_type| content_type.starts_with("application/json"));
+            let body = if json {
+                serde_json::from_slice(response.body()).unwrap_or_default()
+            } else {
+                serde_json::Value::String(String::from_utf8_lossy(response.body()).into_owned())
+            };
+            serde_json::json!({ "status": response.status(), "body": body })
+        })
+        .collect();
+    
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .body(serde_json::Value::Array(results).to_string())
+        .build())
+}
+
+/// Build the request for one batch operation, carrying over the batch's headers
+/// except those describing its own body and encoding
+fn batch_request(batch: &Request, operation: &BatchOperation) -> Result<Request, OracleError> {
+    let (path, _) = operation.path.split_once('?').unwrap_or((&operation.path, ""));
+    let method = match operation.method.trim().to_ascii_uppercase().as_str() {
+        "GET" => Method::Get,
+        "HEAD" => Method::Head,
+        "POST" => Method::Post,
+        _ => return Err(Route::from_path(path).method_not_allowed()),
+    };
+    if !path.starts_with('/') {
+        return Err(OracleError::BadRequest(format!("Batch path must start with '/': {}", operation.path)));
+    }
+    if Route::from_path(path) == Route::Batch {
+        return Err(OracleError::BadRequest("Batches can't be nested".to_string()));
+    }
+    
+    let mut uri = operation.path.clone();
+    let mut body = Vec::new();
+    if method == Method::Post {
+        body = serde_json::to_vec(&operation.params).map_err(anyhow::Error::from)?;
+    } else if !operation.params.is_empty() {
+        let query: Vec<String> = operation
+            .params
+            .iter()
+            .map(|(key, value)| {
+                let value = match value {
+                    serde_json::Value::String(text) => text.clone(),
+                    other => other.to_string(),
+                };
+                format!("{}={}", percent_encode(key), percent_encode(&value))
+            })
+            .collect();
+        uri.push(if uri.contains('?') { '&' } else { '?' });
+        uri.push_str(&query.join("&"));
+    }
+    
+    let mut request = Request::builder().method(method).uri(uri).body(body).build();
+    for (name, value) in batch.headers() {
+        if !matches!(name, "content-length" | "content-type" | "accept-encoding") {
+            if let Some(value) = value.as_str() {
+                request.set_header(name, value);
+            }
+        }
+    }
+    Ok(request)
+}
+
+/// `POST /api/oracle/fortune?source=body`: generate from caller-supplied metrics,
+/// so every haiku band can be exercised without touching the state file
+fn handle_fortune_from_metrics(req: &Request, params: HashMap<String, String>) -> Result<Response> {
+    if params.get("source").map(String::as_str) != Some("body") {
+        return Err(OracleError::BadRequest("POST to the fortune endpoint requires ?source=body".to_string()).into());
+    }
+    
+    let mut metrics: InformationForceMetrics = serde_json::from_slice(req.body())
+        .map_err(|e| OracleError::BadRequest(format!("Failed to parse metrics: {}", e)))?;
+    // Callers don't get to claim the state file (or anything else) as their source
+    metrics.source = None;
+    
+    generate_information_dynamics_fortune(Some(params), accept_header(req), Some(sanitize(metrics)))
+}
+
+/// `GET /api/oracle/fortune?phi=`: metrics derived from a caller's Φ alone, bypassing
+/// the state file and simulation. `None` without the parameter
+fn supplied_phi_metrics(params: &HashMap<String, String>) -> Result<Option<InformationForceMetrics>, OracleError> {
+    let Some(raw) = params.get("phi") else {
+        return Ok(None);
+    };
+    match raw.trim().parse::<f64>() {
+        Ok(phi) if phi.is_finite() && phi >= 0.0 => Ok(Some(InformationForceMetrics {
+            source: Some(MetricSource::Query),
+            ..metrics_from_phi(phi, DEFAULT_QUANTUM_ENTROPY, STATE_CORRELATION)
+        })),
+        _ => Err(OracleError::BadRequest(format!("Invalid phi: {} (expected a finite, non-negative number)", raw))),
+    }
+}
+
+/// Requests per minute allowed per client when `rate_limit_per_minute` is unset
+const DEFAULT_RATE_LIMIT_PER_MINUTE: &str = "30";
+
+/// Token bucket persisted in KV, since each request runs in a fresh component instance
+#[derive(Debug, Serialize, Deserialize)]
+struct TokenBucket {
+    tokens: f64,
+    updated_ms: u64,
+}
+
+/// Real client IP: the first `x-forwarded-for` hop (the proxy appends the rest),
+/// else Spin's peer address. Values that don't parse as an address are ignored.
+fn client_ip(req: &Request) -> Option<IpAddr> {
+    let forwarded = req
+        .header("x-forwarded-for")
+        .and_then(|value| value.as_str())
+        .and_then(|value| value.split(',').next())
+        .and_then(parse_ip);
+    
+    forwarded.or_else(|| parse_ip(req.header("spin-client-addr")?.as_str()?))
+}
+
+/// Parse "ip", "ip:port", "[ipv6]" or "[ipv6]:port"
+fn parse_ip(value: &str) -> Option<IpAddr> {
+    let value = value.trim();
+    value
+        .parse::<IpAddr>()
+        .or_else(|_| value.parse::<SocketAddr>().map(|addr| addr.ip()))
+        .or_else(|_| value.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>())
+        .ok()
+}
+
+/// Consume one token from the caller's bucket, or fail with 429 and a retry hint.
+/// A limit of 0 disables limiting; KV failures fail open so the booth keeps working.
+fn enforce_rate_limit(req: &Request) -> Result<(), OracleError> {
+    let per_minute: f64 = variable_or("rate_limit_per_minute", DEFAULT_RATE_LIMIT_PER_MINUTE)
+        .parse()
+        .unwrap_or(0.0);
+    if per_minute <= 0.0 {
+        return Ok(());
+    }
+    let Some(client) = client_ip(req) else {
+        return Ok(());
+    };
+    
+    match take_token(&client, per_minute) {
+        Ok(None) => Ok(()),
+        Ok(Some(retry_after_secs)) => {
+            log!(info, "Rate limited {}", client);
+            Err(OracleError::RateLimited { retry_after_secs })
+        }
+        Err(e) => {
+            log!(warn, "Rate limiter unavailable, allowing request: {}", e);
+            Ok(())
+        }
+    }
+}
+
+/// Refill and draw from the client's bucket; returns seconds to wait if it was empty
+fn take_token(client: &IpAddr, per_minute: f64) -> Result<Option<u64>> {
+    let store = Store::open_default()?;
+    let key = format!("ratelimit:{}", client);
+    let bucket = store.get(&key)?.and_then(|json| serde_json::from_slice(&json).ok());
+    
+    let (bucket, retry_after) = draw_token(bucket, get_current_timestamp(), per_minute);
+    store.set(&key, &serde_json::to_vec(&bucket)?)?;
+    Ok(retry_after)
+}
+
+/// Refill `bucket` (a new client starts full) up to `now` and take a token from it.
+/// Returns the updated bucket and, when it was empty, the seconds until a token is due
+fn draw_token(bucket: Option<TokenBucket>, now: u64, per_minute: f64) -> (TokenBucket, Option<u64>) {
+    let refill_per_ms = per_minute / 60_000.0;
+    let mut bucket = bucket.unwrap_or(TokenBucket { tokens: per_minute, updated_ms: now });
+    let elapsed_ms = now.saturating_sub(bucket.updated_ms) as f64;
+    bucket.tokens = (bucket.tokens + elapsed_ms * refill_per_ms).min(per_minute);
+    bucket.updated_ms = now;
+    
+    let retry_after = if bucket.tokens >= 1.0 {
+        bucket.tokens -= 1.0;
+        None
+    } else {
+        Some((((1.0 - bucket.tokens) / refill_per_ms) / 1000.0).ceil().max(1.0) as u64)
+    };
+    (bucket, retry_after)
+}
+
+/// Seconds browsers may cache a preflight result
+const CORS_MAX_AGE_SECS: u32 = 600;
+
+/// Preflight response advertising what the route accepts; the allow-origin
+/// decision is added by `route_request`
+fn handle_cors_preflight(route: Route) -> Result<Response> {
+    Ok(Response::builder()
+        .status(200)
+        .header("access-control-allow-methods", route.allowed_methods())
+        .header("access-control-allow-headers", "content-type, authorization, x-request-id")
+        .header("access-control-max-age", CORS_MAX_AGE_SECS.to_string())
+        .body("")
+        .build())
+}
+
+/// Seconds clients are told to wait while `maintenance_mode` is on
+const MAINTENANCE_RETRY_AFTER_SECS: u64 = 60;
+
+/// Whether the oracle is refusing traffic for setup or teardown (`maintenance_mode`)
+fn maintenance_mode() -> bool {
+    variable_or("maintenance_mode", "false").parse().unwrap_or(false)
+}
+
+/// KV key holding when the state file was last written (epoch ms), or when the
+/// probe first found it missing if it has never seen it
+const STATE_SEEN_KEY: &str = "health:state_seen_at";
+
+/// Whether the state counts as healthy, and the new `STATE_SEEN_KEY` value when it
+/// moved. A missing file is tolerated until `STATE_FRESHNESS_SECS` after it was last
+/// written, so the Oracle replacing it or a brief unmount doesn't fail the probe
+fn state_health(age: Option<Duration>, seen_at: Option<u64>, now: u64) -> (bool, Option<u64>) {
+    match (age, seen_at) {
+        (Some(age), _) => {
+            let written_at = now.saturating_sub(u64::try_from(age.as_millis()).unwrap_or(u64::MAX));
+            // Ignore sub-second jitter between the two clocks rather than rewrite KV every probe
+            let moved = seen_at.map_or(true, |seen_at| seen_at.abs_diff(written_at) >= 1000);
+            (age.as_secs() < STATE_FRESHNESS_SECS, moved.then_some(written_at))
+        }
+        (None, Some(seen_at)) => (now.saturating_sub(seen_at) < STATE_FRESHNESS_SECS * 1000, None),
+        (None, None) => (true, Some(now)),
+    }
+}
+
+/// Cheap liveness/readiness probe - stats the state file but never parses it
+fn get_health() -> Result<Response> {
+    if maintenance_mode() {
+        let health = serde_json::json!({ "status": "maintenance" });
+        return Ok(Response::builder()
+            .status(503)
+            .header("content-type", "application/json")
+            .header("cache-control", "no-store")
+            .header("retry-after", MAINTENANCE_RETRY_AFTER_SECS.to_string())
+            .body(health.to_string())
+            .build());
+    }
+    
+    let state_age = FileStateSource.age();
+    let state_file_present = state_age.is_some();
+    let clock_ok = get_current_timestamp() > 0;
+    
+    // Degraded once the Oracle has stopped writing state for longer than the freshness
+    // window, whether the file is still there or not
+    let healthy = match Store::open_default() {
+        Ok(store) => {
+            let seen_at = store
+                .get(STATE_SEEN_KEY)
+                .ok()
+                .flatten()
+                .and_then(|value| String::from_utf8(value).ok())
+                .and_then(|value| value.parse().ok());
+            let (healthy, update) = state_health(state_age, seen_at, get_current_timestamp());
+            if let Some(seen_at) = update {
+                if let Err(e) = store.set(STATE_SEEN_KEY, seen_at.to_string().as_bytes()) {
+                    log!(warn, "Could not record when the state file was seen: {}", e);
+                }
+            }
+            healthy
+        }
+        Err(e) => {
+            log!(warn, "Could not track state file health: {}", e);
+            matches!(state_age, Some(age) if age.as_secs() < STATE_FRESHNESS_SECS)
+        }
+    };
+    
+    let health = serde_json::json!({
+        "status": if healthy { "ok" } else { "degraded" },
+        "state_file_present": state_file_present,
+        "clock_ok": clock_ok,
+    });
+    
+    Ok(Response::builder()
+        .status(if healthy { 200 } else { 503 })
+        .header("content-type", "application/json")
+        .header("cache-control", "no-store")
+        .body(health.to_string())
+        .build())
+}
+
+/// Build metadata for coordinating deployments; `build.rs` supplies the git SHA and
+/// build time, which read "unknown" when it couldn't determine them
+fn get_version() -> Result<Response> {
+    let build_time = option_env!("ORACLE_BUILD_TIME")
+        .and_then(|secs| secs.parse::<u64>().ok())
+        .map_or_else(|| "unknown".to_string(), |secs| format_timestamp_iso(secs * 1000));
+    let version = serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "git_sha": option_env!("ORACLE_GIT_SHA").unwrap_or("unknown"),
+        "build_time": build_time,
+    });
+    
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .body(version.to_string())
+        .build())
+}
+
+fn get_information_dynamics_status(req: &Request) -> Result<Response> {
+    let metrics = calculate_information_dynamics_metrics()?;
+    let tri_loop_status = assess_tri_loop_system(state_source());
+    
+    let status = serde_json::json!({
+        "information-dynamics": metrics,
+        "tri_loop": tri_loop_status,
+        "system_ready": metrics.threshold_exceeded,
+        "burning_man_mode": burning_man_mode(),
+        "gift_economy_active": burning_man_mode()
+    });
+    
+    Ok(cacheable_json(req, status.to_string()))
+}
+
+/// Smoothing factor used when `smoothing_alpha` is unset
+const DEFAULT_SMOOTHING_ALPHA: &str = "0.3";
+
+/// Smallest usable smoothing factor; at zero the EMA would never move
+const MIN_SMOOTHING_ALPHA: f64 = 0.001;
+
+/// A `true`/`false` query flag, false when absent
+fn flag_param(params: &HashMap<String, String>, name: &str) -> Result<bool, OracleError> {
+    match params.get(name) {
+        Some(value) => value
+            .trim()
+            .parse::<bool>()
+            .map_err(|_| OracleError::BadRequest(format!("Invalid {} flag: {}", name, value))),
+        None => Ok(false),
+    }
+}
+
+/// Short keys for `?compact=true`, as (verbose, compact). Fields not listed keep their names
+const COMPACT_METRIC_KEYS: &[(&str, &str)] = &[
+    ("semantic_closure", "sc"),
+    ("strange_loops", "sl"),
+    ("hofstadter_coefficient", "hc"),
+    ("spectral_gap", "sg"),
+    ("correlation_strength", "cs"),
+    ("threshold_exceeded", "te"),
+];
+
+/// Serialized metrics with their keys shortened per `COMPACT_METRIC_KEYS`
+fn compact_metrics(metrics: &InformationForceMetrics) -> Result<serde_json::Value> {
+    let mut value = serde_json::to_value(metrics)?;
+    if let Some(object) = value.as_object_mut() {
+        for (verbose, compact) in COMPACT_METRIC_KEYS {
+            if let Some(field) = object.remove(*verbose) {
+                object.insert(compact.to_string(), field);
+            }
+        }
+    }
+    Ok(value)
+}
+
+fn get_live_metrics(req: &Request, params: HashMap<String, String>) -> Result<Response> {
+    let smooth = flag_param(&params, "smooth")?;
+    let compact = flag_param(&params, "compact")?;
+    
+    let mut metrics = calculate_information_dynamics_metrics()?;
+    if smooth {
+        let alpha = variable_or("smoothing_alpha", DEFAULT_SMOOTHING_ALPHA)
+            .parse()
+            .unwrap_or(f64::NAN);
+        let history = Store::open_default()
+            .map_err(anyhow::Error::from)
+            .and_then(|store| load_metrics_history(&store))
+            .unwrap_or_default();
+        metrics = smooth_metrics(metrics, &history, alpha);
+    }
+    
+    let body = if compact {
+        compact_metrics(&metrics)?.to_string()
+    } else {
+        serde_json::to_string(&metrics)?
+    };
+    Ok(cacheable_json(req, body))
+}
+
+/// Seconds clients may reuse a metrics response before revalidating
+const METRICS_MAX_AGE_SECS: u32 = 2;
+
+/// JSON response with a weak ETag over the body; 304 when `If-None-Match` already has it
+fn cacheable_json(req: &Request, body: String) -> Response {
+    let etag = format!("W/\"{:016x}\"", fnv1a64(body.as_bytes(), FNV_OFFSET_BASIS));
+    let cache_control = format!("max-age={}", METRICS_MAX_AGE_SECS);
+    
+    // Weak comparison: the W/ prefix is ignored on both sides
+    let not_modified = req
+        .header("if-none-match")
+        .and_then(|value| value.as_str())
+        .is_some_and(|candidates| {
+            candidates.split(',').map(str::trim).any(|candidate| {
+                candidate == "*" || candidate.trim_start_matches("W/") == etag.trim_start_matches("W/")
+            })
+        });
+    
+    let mut response = Response::builder();
+    response
+        .header("etag", etag.as_str())
+        .header("cache-control", cache_control);
+    if not_modified {
+        response.status(304).body(Vec::new()).build()
+    } else {
+        response
+            .status(200)
+            .header("content-type", "application/json")
+            .body(body)
+            .build()
+    }
+}
+
+/// Samples kept in the metrics history when `metrics_history_size` is unset
+const DEFAULT_METRICS_HISTORY_SIZE: &str = "256";
+
+/// KV key holding the metrics history ring buffer
+const METRICS_HISTORY_KEY: &str = "metrics:history";
+
+/// One entry in the metrics history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MetricsSample {
+    timestamp: u64,
+    metrics: InformationForceMetrics,
+}
+
+/// Capacity of the metrics history (Spin variable `metrics_history_size`); 0 disables it
+fn metrics_history_capacity() -> usize {
+    variable_or("metrics_history_size", DEFAULT_METRICS_HISTORY_SIZE)
+        .parse()
+        .unwrap_or(0)
+}
+
+/// Metrics history, oldest first
+fn load_metrics_history(store: &Store) -> Result<VecDeque<MetricsSample>> {
+    match store.get(METRICS_HISTORY_KEY)? {
+        Some(json) => Ok(serde_json::from_slice(&json)?),
+        None => Ok(VecDeque::new()),
+    }
+}
+
+/// Append a sample, evicting the oldest beyond capacity.
+/// KV has no atomic update, so concurrent writers may drop each other's sample.
+fn record_metrics_sample(metrics: &InformationForceMetrics) {
+    let capacity = metrics_history_capacity();
+    if capacity == 0 {
+        return;
+    }
+    
+    let recorded = Store::open_default()
+        .map_err(anyhow::Error::from)
+        .and_then(|store| {
+            let mut history = load_metrics_history(&store).unwrap_or_default();
+            history.push_back(MetricsSample {
+                timestamp: get_current_timestamp(),
+                metrics: metrics.clone(),
+            });
+            while history.len() > capacity {
+                history.pop_front();
+            }
+            store.set(METRICS_HISTORY_KEY, &serde_json::to_vec(&history)?)?;
+            Ok(())
+        });
+    
+    if let Err(e) = recorded {
+        log!(warn, "Could not record metrics history: {}", e);
+    }
+}
+
+/// KV key remembering whether the threshold was exceeded on the previous read
+const THRESHOLD_STATE_KEY: &str = "threshold:exceeded";
+
+/// Attempts per webhook delivery, including the first
+const WEBHOOK_MAX_ATTEMPTS: u32 = 3;
+
+/// Delay before the first webhook retry; doubled for each one after
+const WEBHOOK_INITIAL_BACKOFF_MS: u64 = 250;
+
+/// POST the metrics to `threshold_webhook_url` when the threshold goes from
+/// not exceeded to exceeded. The URL's host must be in `allowed_outbound_hosts`.
+/// Delivery happens after the response is sent; failures are only logged.
+fn notify_threshold_crossing(metrics: &InformationForceMetrics, source: &dyn StateSource) {
+    let url = variable_or("threshold_webhook_url", "");
+    if url.is_empty() {
+        return;
+    }
+    
+    let store = match Store::open_default() {
+        Ok(store) => store,
+        Err(e) => {
+            log!(warn, "Could not track threshold state: {}", e);
+            return;
+        }
+    };
+    
+    // Without a recorded previous state there is no edge to report
+    let previous = match store.get(THRESHOLD_STATE_KEY) {
+        Ok(value) => value.map(|value| value == b"true"),
+        Err(e) => {
+            log!(warn, "Could not read threshold state: {}", e);
+            return;
+        }
+    };
+    if previous == Some(metrics.threshold_exceeded) {
+        return;
+    }
+    
+    let current: &[u8] = if metrics.threshold_exceeded { b"true" } else { b"false" };
+    if let Err(e) = store.set(THRESHOLD_STATE_KEY, current) {
+        log!(warn, "Could not record threshold state: {}", e);
+        return;
+    }
+    
+    if previous == Some(false) && metrics.threshold_exceeded {
+        log!(info, "Information-dynamics threshold crossed - notifying webhook");
+        match serde_json::to_vec(&webhook_payload(webhook_format(), metrics, source)) {
+            Ok(body) => queue_webhook(&url, body),
+            Err(e) => log!(warn, "Could not serialize metrics for webhook: {}", e),
+        }
+    }
+}
+
+/// Payload shapes for the threshold webhook
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WebhookFormat {
+    /// The metrics object as-is
+    Raw,
+    /// A Discord embed with the haiku, closure and a closure-derived colour
+    Discord,
+    /// Slack Block Kit sections
+    Slack,
+}
+
+/// The `webhook_format` variable; unknown values fall back to `raw`
+fn webhook_format() -> WebhookFormat {
+    match variable_or("webhook_format", "raw").to_ascii_lowercase().as_str() {
+        "raw" => WebhookFormat::Raw,
+        "discord" => WebhookFormat::Discord,
+        "slack" => WebhookFormat::Slack,
+        other => {
+            log!(warn, "Unknown webhook_format {} - posting raw metrics", other);
+            WebhookFormat::Raw
+        }
+    }
+}
+
+fn webhook_payload(format: WebhookFormat, metrics: &InformationForceMetrics, source: &dyn StateSource) -> serde_json::Value {
+    use serde_json::json;
+    
+    let closure = fmt_pct(metrics.semantic_closure);
+    match format {
+        WebhookFormat::Raw => json!(metrics),
+        WebhookFormat::Discord => json!({
+            "embeds": [{
+                "title": "Zeldar threshold crossed",
+                "description": webhook_haiku(metrics, source).join("\n"),
+                "color": closure_color(metrics.semantic_closure),
+                "fields": [{ "name": "Semantic closure", "value": closure, "inline": true }],
+            }],
+        }),
+        WebhookFormat::Slack => json!({
+            "text": format!("Zeldar threshold crossed at {} semantic closure", closure),
+            "blocks": [
+                { "type": "section", "text": { "type": "mrkdwn", "text": webhook_haiku(metrics, source).join("\n") } },
+                { "type": "context", "elements": [{ "type": "mrkdwn", "text": format!("Semantic closure: *{}*", closure) }] },
+            ],
+        }),
+    }
+}
+
+/// The haiku announcing a crossing: the Oracle's own if it has a valid one,
+/// else the English template for the closure band
+fn webhook_haiku(metrics: &InformationForceMetrics, source: &dyn StateSource) -> Vec<String> {
+    let oracle_haiku = source
+        .load()
+        .ok()
+        .flatten()
+        .and_then(|state| state.haiku_content)
+        .map(|content| split_haiku_lines(&content))
+        .filter(|lines| is_valid_haiku(lines));
+    
+    oracle_haiku.unwrap_or_else(|| {
+        let band_theme = closure_band_theme(metrics.semantic_closure);
+        let pool = template_pool(DEFAULT_LANGUAGE);
+        let index = pool.iter().position(|template| template.theme == band_theme).unwrap_or(0);
+        pool[index].lines.to_vec()
+    })
+}
+
+/// Embed colour blending the interface's navy (closure 0) into its accent red (closure 1)
+fn closure_color(semantic_closure: f64) -> u32 {
+    const LOW: [u8; 3] = [0x0f, 0x34, 0x60];
+    const HIGH: [u8; 3] = [0xe9, 0x45, 0x60];
+    
+    let t = semantic_closure.clamp(0.0, 1.0);
+    LOW.iter().zip(HIGH).fold(0, |color, (&low, high)| {
+        let channel = f64::from(low) + (f64::from(high) - f64::from(low)) * t;
+        (color << 8) | channel.round() as u32
+    })
+}
+
+/// Webhook POSTs (URL and body) waiting for the response to go out
+static PENDING_WEBHOOKS: Mutex<Vec<(String, Vec<u8>)>> = Mutex::new(Vec::new());
+
+fn queue_webhook(url: &str, body: Vec<u8>) {
+    PENDING_WEBHOOKS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .push((url.to_string(), body));
+}
+
+fn take_pending_webhooks() -> Vec<(String, Vec<u8>)> {
+    std::mem::take(&mut *PENDING_WEBHOOKS.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+}
+
+/// Send the webhooks queued while handling the request. Called once the response
+/// is out, so slow receivers and retry backoff never delay the client
+async fn deliver_pending_webhooks() {
+    for (url, body) in take_pending_webhooks() {
+        if let Err(e) = post_json_with_retry(&url, body).await {
+            log!(warn, "Threshold webhook failed: {:#}", e);
+        }
+    }
+}
+
+/// POST a JSON body, retrying failures and non-2xx responses with exponential backoff
+async fn post_json_with_retry(url: &str, body: Vec<u8>) -> Result<()> {
+    let mut backoff = Duration::from_millis(WEBHOOK_INITIAL_BACKOFF_MS);
+    let mut attempt = 1;
+    
+    loop {
+        let request = Request::post(url, body.clone())
+            .header("content-type", "application/json")
+            .build();
+        let outcome = spin_sdk::http::send::<_, Response>(request)
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|response| match *response.status() {
+                200..=299 => Ok(()),
+                status => Err(anyhow::anyhow!("webhook responded with status {}", status)),
+            });
+        
+        match outcome {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt >= WEBHOOK_MAX_ATTEMPTS => {
+                return Err(e.context(format!("gave up after {} attempts", attempt)));
+            }
+            Err(e) => {
+                log!(warn, "Webhook attempt {} failed ({}) - retrying in {:?}", attempt, e, backoff);
+                sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Wait on a monotonic-clock pollable, letting the executor serve other futures meanwhile
+async fn sleep(duration: Duration) {
+    use spin_sdk::wit::wasi::clocks0_2_0::monotonic_clock;
+    
+    let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+    let deadline = monotonic_clock::now().saturating_add(nanos);
+    futures::future::poll_fn(|context| {
+        if monotonic_clock::now() >= deadline {
+            return std::task::Poll::Ready(());
+        }
+        spin_executor::push_waker(monotonic_clock::subscribe_instant(deadline), context.waker().clone());
+        std::task::Poll::Pending
+    })
+    .await
+}
+
+/// Representations of the metrics history
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HistoryFormat {
+    Json,
+    Ndjson,
+    Csv,
+}
+
+/// Columns of the CSV history export
+const HISTORY_CSV_HEADER: [&str; 7] = [
+    "timestamp",
+    "semantic_closure",
+    "strange_loops",
+    "hofstadter_coefficient",
+    "spectral_gap",
+    "correlation_strength",
+    "threshold_exceeded",
+];
+
+/// RFC 4180 CSV with a header row, one row per sample and booleans as 0/1
+fn render_history_csv(samples: &[MetricsSample]) -> String {
+    let mut csv = String::new();
+    let mut push_row = |fields: &[String]| {
+        let escaped: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&escaped.join(","));
+        csv.push_str("\r\n");
+    };
+    
+    push_row(&HISTORY_CSV_HEADER.map(str::to_string));
+    for sample in samples {
+        let metrics = &sample.metrics;
+        push_row(&[
+            sample.timestamp.to_string(),
+            metrics.semantic_closure.to_string(),
+            metrics.strange_loops.to_string(),
+            metrics.hofstadter_coefficient.to_string(),
+            metrics.spectral_gap.to_string(),
+            metrics.correlation_strength.to_string(),
+            u8::from(metrics.threshold_exceeded).to_string(),
+        ]);
+    }
+    csv
+}
+
+/// Quote a CSV field when it holds a delimiter, quote or line break, doubling inner quotes
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Most recent metrics samples, newest first, at most `?limit=` of them.
+/// `?format=ndjson` writes one sample per line, each line ending in a newline.
+fn get_metrics_history(params: HashMap<String, String>) -> Result<Response> {
+    let format = match params.get("format").map(|format| format.trim().to_ascii_lowercase()).as_deref() {
+        None | Some("json") => HistoryFormat::Json,
+        Some("ndjson") => HistoryFormat::Ndjson,
+        Some("csv") => HistoryFormat::Csv,
+        Some(other) => return Err(OracleError::BadRequest(format!("Unsupported format: {}", other)).into()),
+    };
+    let limit = match params.get("limit") {
+        Some(limit) => limit
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| OracleError::BadRequest(format!("Invalid limit: {}", limit)))?,
+        None => metrics_history_capacity(),
+    };
+    // Pollers pass the newest timestamp they hold to fetch just the delta
+    let since = match params.get("since") {
+        Some(since) => since
+            .trim()
+            .parse::<u64>()
+            .map_err(|_| OracleError::BadRequest(format!("Invalid since: {}", since)))?,
+        None => 0,
+    };
+    let compact = flag_param(&params, "compact")?;
+    
+    let store = Store::open_default()?;
+    let samples: Vec<MetricsSample> = load_metrics_history(&store)?
+        .into_iter()
+        .rev()
+        .filter(|sample| sample.timestamp > since)
+        .take(limit)
+        .collect();
+    
+    // CSV already has a single header row, so compact only changes the JSON forms
+    let encoded = samples
+        .iter()
+        .map(|sample| {
+            if compact {
+                Ok(serde_json::json!({ "timestamp": sample.timestamp, "metrics": compact_metrics(&sample.metrics)? }))
+            } else {
+                Ok(serde_json::to_value(sample)?)
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+    
+    let (content_type, body) = match format {
+        HistoryFormat::Json => ("application/json", serde_json::to_string(&encoded)?),
+        HistoryFormat::Ndjson => {
+            let mut lines = String::new();
+            for sample in &encoded {
+                lines.push_str(&sample.to_string());
+                lines.push('\n');
+            }
+            ("application/x-ndjson", lines)
+        }
+        HistoryFormat::Csv => ("text/csv", render_history_csv(&samples)),
+    };
+    
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", content_type)
+        .header("cache-control", "no-store")
+        .body(body)
+        .build())
+}
+
+/// Replace closure and correlation with their EMA over the history, keeping the raw values.
+/// The history already ends with the current sample; without any, metrics pass through unchanged.
+fn smooth_metrics(metrics: InformationForceMetrics, history: &VecDeque<MetricsSample>, alpha: f64) -> InformationForceMetrics {
+    // Clamp into (0, 1]; an unparseable alpha means no smoothing at all
+    let alpha = if alpha.is_nan() { 1.0 } else { alpha.clamp(MIN_SMOOTHING_ALPHA, 1.0) };
+    
+    let closure = exponential_moving_average(history.iter().map(|sample| sample.metrics.semantic_closure), alpha);
+    let correlation = exponential_moving_average(history.iter().map(|sample| sample.metrics.correlation_strength), alpha);
+    
+    match (closure, correlation) {
+        (Some(closure), Some(correlation)) => InformationForceMetrics {
+            raw_semantic_closure: Some(metrics.semantic_closure),
+            raw_correlation_strength: Some(metrics.correlation_strength),
+            semantic_closure: closure,
+            correlation_strength: correlation,
+            ..metrics
+        },
+        _ => metrics,
+    }
+}
+
+/// EMA of a series (oldest first), seeded with its first value; `None` when empty
+fn exponential_moving_average(values: impl IntoIterator<Item = f64>, alpha: f64) -> Option<f64> {
+    values
+        .into_iter()
+        .reduce(|average, value| alpha * value + (1.0 - alpha) * average)
+}
+
+/// Seconds between metrics events on the SSE stream
+const STREAM_INTERVAL_SECS: u64 = 2;
+
+/// Longest a metrics stream stays open when `sse_max_duration_secs` is unset
+const DEFAULT_SSE_MAX_DURATION_SECS: u64 = 3600;
+
+/// Lifetime cap for one metrics stream, so abandoned connections don't linger; 0 disables it
+fn sse_max_duration() -> Option<Duration> {
+    let secs = variable_or("sse_max_duration_secs", "").parse().unwrap_or(DEFAULT_SSE_MAX_DURATION_SECS);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Why the server ended a metrics stream, sent as the `data:` of the final `event: close`
+fn stream_close_reason(opened: Instant, max_duration: Option<Duration>) -> Option<&'static str> {
+    if maintenance_mode() {
+        Some("maintenance")
+    } else if max_duration.is_some_and(|max| opened.elapsed() >= max) {
+        Some("max_duration")
+    } else {
+        None
+    }
+}
+
+/// Server-Sent Events feed of live metrics, one `event: metrics` every two seconds.
+///
+/// Each event carries an incrementing `id:`. Browsers' `EventSource` reconnects on its
+/// own after a dropped connection (we advise a `retry:` of one interval) and sends the
+/// last id it saw as `Last-Event-ID`; numbering resumes after that id. The loop ends as
+/// soon as a write fails, which is how a client disconnect surfaces to the component.
+/// The server ends it with a final `event: close` when maintenance mode switches on
+/// or the stream reaches `sse_max_duration_secs`.
+async fn stream_live_metrics(req: &Request, request_id: &str, started: Instant, response_out: ResponseOutparam) {
+    let mut header_list = vec![
+        ("content-type".to_string(), b"text/event-stream".to_vec()),
+        ("cache-control".to_string(), b"no-cache".to_vec()),
+        ("x-request-id".to_string(), request_id.as_bytes().to_vec()),
+    ];
+    for (name, value) in cors_headers(req) {
+        header_list.push((name.to_string(), value.into_bytes()));
+    }
+    header_list.push(("x-response-time-ms".to_string(), response_time_ms(started).into_bytes()));
+    let headers = Fields::from_list(&header_list);
+    let response = match headers {
+        Ok(headers) => OutgoingResponse::new(headers),
+        Err(e) => {
+            log!(warn, "Could not build stream headers: {:?}", e);
+            let mut response = OracleError::Internal("Could not open metrics stream".to_string()).into_response();
+            response.set_header("x-request-id", request_id);
+            response.set_header("x-response-time-ms", response_time_ms(started));
+            return send_response(response_out, response).await;
+        }
+    };
+    // A fresh OutgoingResponse defaults to 200 OK
+    let body = response.take_body();
+    response_out.set(response);
+    
+    let last_event_id: u64 = req
+        .header("last-event-id")
+        .and_then(|value| value.as_str())
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(0);
+    
+    write_metrics_events(body, last_event_id, started, || sleep(Duration::from_secs(STREAM_INTERVAL_SECS))).await;
+}
+
+/// The stream's event loop, numbering events after `last_event_id`. It writes to any
+/// sink and waits on `pause` between ticks, so tests can drive it outside Spin
+async fn write_metrics_events<S, P, F>(body: S, last_event_id: u64, started: Instant, pause: P)
+where
+    S: futures::Sink<Vec<u8>>,
+    P: Fn() -> F,
+    F: std::future::Future<Output = ()>,
+{
+    use futures::SinkExt;
+    
+    let mut body = std::pin::pin!(body);
+    let mut event_id = last_event_id;
+    let max_duration = sse_max_duration();
+    let mut preamble = format!("retry: {}\n\n", STREAM_INTERVAL_SECS * 1000).into_bytes();
+    loop {
+        if let Some(reason) = stream_close_reason(started, max_duration) {
+            let mut event = std::mem::take(&mut preamble);
+            event.extend(format!("event: close\ndata: {}\n\n", reason).into_bytes());
+            if body.send(event).await.is_err() {
+                log!(info, "Metrics stream client disconnected before close");
+            }
+            log!(info, "Closed metrics stream after {} events: {}", event_id, reason);
+            break;
+        }
+        
+        // Ticks only observe: recording a sample (and notifying on a crossing) every
+        // two seconds per open stream would flood the history ring buffer
+        let metrics = match read_information_dynamics_metrics(state_source(), metrics_mode()) {
+            Ok(metrics) => metrics,
+            Err(e) => {
+                let mut event = std::mem::take(&mut preamble);
+                event.extend(b"event: close\ndata: unavailable\n\n");
+                let _ = body.send(event).await;
+                log!(warn, "Closed metrics stream after {} events: {}", event_id, e);
+                break;
+            }
+        };
+        event_id += 1;
+        let data = match serde_json::to_string(&metrics) {
+            Ok(data) => data,
+            Err(e) => {
+                log!(warn, "Could not encode stream metrics: {}", e);
+                break;
+            }
+        };
+        
+        let mut event = std::mem::take(&mut preamble);
+        event.extend(format!("id: {}\nevent: metrics\ndata: {}\n\n", event_id, data).into_bytes());
+        if body.send(event).await.is_err() {
+            log!(info, "Metrics stream client disconnected after {} events", event_id);
+            break;
+        }
+        
+        pause().await;
+    }
+}
+
+fn get_prometheus_metrics() -> Result<Response> {
+    let metrics = calculate_information_dynamics_metrics()?;
+    
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "text/plain; version=0.0.4")
+        .body(render_prometheus(&metrics))
+        .build())
+}
+
+/// Render metrics as Prometheus text exposition format (one gauge per field)
+fn render_prometheus(metrics: &InformationForceMetrics) -> String {
+    let gauges: [(&str, &str, f64); 6] = [
+        ("oracle_semantic_closure", "Semantic closure of the tri-loop system (0-1)", metrics.semantic_closure),
+        ("oracle_strange_loops", "Number of detected strange loops", metrics.strange_loops as f64),
+        ("oracle_hofstadter_coefficient", "Hofstadter self-reference coefficient", metrics.hofstadter_coefficient),
+        ("oracle_spectral_gap", "Expander graph spectral gap", metrics.spectral_gap),
+        ("oracle_correlation_strength", "Tri-loop correlation strength (0-1)", metrics.correlation_strength),
+        ("oracle_threshold_exceeded", "Whether the information-dynamics threshold is exceeded (0/1)", if metrics.threshold_exceeded { 1.0 } else { 0.0 }),
+    ];
+    
+    let mut out = String::new();
+    for (name, help, value) in gauges {
+        out.push_str(&format!("# HELP {} {}\n# TYPE {} gauge\n{} {}\n", name, help, name, name, prometheus_value(value)));
+    }
+    out
+}
+
+/// Format a sample value, spelling non-finite floats the way Prometheus expects
+fn prometheus_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Generate from `metrics` when supplied, otherwise from the live Oracle metrics
+fn generate_information_dynamics_fortune(
+    params: Option<HashMap<String, String>>,
+    accept: Option<&str>,
+    metrics: Option<InformationForceMetrics>,
+) -> Result<Response> {
+    let mut params = GenerationParams::from_map(&params.unwrap_or_default())
+        .map_err(OracleError::BadRequest)?;
+    let format = FortuneFormat::select(params.format.as_deref(), accept)?;
+    
+    // Supplied metrics drive selection on their own, without the state file's haiku
+    params.live &= metrics.is_none();
+    check_acrostic(&params)?;
+    let metrics = match metrics {
+        Some(metrics) => metrics,
+        None => calculate_information_dynamics_metrics()?,
+    };
+    if params.require_threshold && !metrics.threshold_exceeded {
+        return Ok(Response::builder().status(204).body(()).build());
+    }
+    
+    let mut fortunes = build_fortunes(&params, metrics, state_source());
+    record_generated_fortunes(&mut fortunes);
+    
+    render_fortunes(&fortunes, format)
+}
+
+/// Everything that follows generation on every fortune endpoint: permalinks, the
+/// analytics counters and the NATS feed
+fn record_generated_fortunes(fortunes: &mut [FortuneResponse]) {
+    for fortune in fortunes.iter_mut() {
+        persist_fortune(fortune);
+    }
+    record_fortune_stats(fortunes);
+    queue_fortune_publications(fortunes);
+}
+
+/// Generate the fortune(s) described by `params` without persisting or rendering them
+fn build_fortunes(
+    params: &GenerationParams,
+    information_dynamics: InformationForceMetrics,
+    source: &dyn StateSource,
+) -> Vec<FortuneResponse> {
+    let tri_loop = assess_tri_loop_system(source);
+    let mechanism = select_generation_mechanism(&information_dynamics, params.seed);
+    let timestamp = get_current_timestamp();
+    
+    let acrostic = params.acrostic.as_deref().and_then(build_acrostic);
+    let note = params
+        .acrostic
+        .as_deref()
+        .filter(|word| acrostic.is_some() && word.trim().chars().count() > MAX_ACROSTIC_LETTERS)
+        .map(|word| {
+            let kept: String = word.trim().chars().take(MAX_ACROSTIC_LETTERS).collect();
+            format!("Acrostic word truncated to \"{}\" - a haiku has three lines", kept.to_uppercase())
+        });
+    
+    // Acrostic lines are curated in English only
+    let lang = if acrostic.is_some() { DEFAULT_LANGUAGE } else { params.language() };
+    // Community haiku are validated as English, so only English fortunes draw on them
+    let contributed = if lang == "en" { load_approved_contributions() } else { Vec::new() };
+    
+    // Template indices already handed out in this bundle, so each fortune differs
+    let mut chosen = HashSet::new();
+    
+    // Seeded and caller-driven selection must stay reproducible, so only live,
+    // unseeded requests avoid what the previous visitors just got
+    let window = anti_repetition_window();
+    let avoid_recent = params.live && params.seed.is_none() && window > 0;
+    let recent = if avoid_recent { load_recent_templates() } else { Vec::new() };
+    // Rare haiku are English, like the community pool
+    let rare_chance = rare_fortune_chance();
+    
+    let fortunes: Vec<FortuneResponse> = (0..params.count as usize)
+        .map(|position| {
+            // Generate information-dynamics-aware haiku; a seed always draws from the
+            // templates so the same seed gives the same haiku whatever the live metrics
+            let rare = if acrostic.is_none() && lang == "en" { roll_rare_haiku(params.seed, position, rare_chance) } else { None };
+            let choice = if let Some(lines) = &acrostic {
+                HaikuChoice { lines: lines.to_vec(), theme: None, origin: HaikuOrigin::Acrostic, pool_index: None }
+            } else if let Some(lines) = rare {
+                HaikuChoice { lines: lines.map(str::to_string).to_vec(), theme: None, origin: HaikuOrigin::Rare, pool_index: None }
+            } else if draws_emergent(params, &information_dynamics, position) {
+                generate_information_dynamics_haiku(&information_dynamics, params, source, &contributed, &recent, position, &mut chosen)
+            } else {
+                HaikuChoice { lines: generate_standard_haiku(lang), theme: None, origin: HaikuOrigin::Standard, pool_index: None }
+            };
+            let explanation = params.explain.then(|| explain_selection(params, &information_dynamics, &choice));
+            let haiku = choice.lines;
+            
+            // Only reported when the caller asked for a theme
+            let theme_matched = params
+                .theme
+                .as_ref()
+                .map(|requested| choice.theme.is_some_and(|theme| theme.eq_ignore_ascii_case(requested.trim())));
+            
+            let syllables = (params.validate && lang == "en")
+                .then(|| haiku.iter().map(|line| count_syllables(line)).collect());
+            
+            FortuneResponse {
+                id: fortune_id(params.seed.unwrap_or(timestamp), position, &haiku, &mechanism, params.nonce.as_deref()),
+                haiku,
+                mechanism: mechanism.clone(),
+                mechanism_explanation: params
+                    .explain_mechanism
+                    .then(|| mechanism_explanation(&mechanism).map(str::to_string))
+                    .flatten(),
+                information_dynamics: information_dynamics.clone(),
+                timestamp,
+                timestamp_iso: (params.time_format == TimeFormat::Iso).then(|| format_timestamp_iso(timestamp)),
+                tri_loop_status: tri_loop.clone(),
+                syllables,
+                theme: choice.theme.map(str::to_string),
+                theme_matched,
+                date: None,
+                lang: lang.to_string(),
+                note: note.clone(),
+                explanation,
+                request_nonce: params.nonce.clone(),
+                rare: choice.origin == HaikuOrigin::Rare,
+                short_code: None,
+            }
+        })
+        .collect();
+    
+    if avoid_recent && !chosen.is_empty() {
+        let mut bundle: Vec<usize> = chosen.into_iter().collect();
+        bundle.sort_unstable();
+        remember_recent_templates(recent, bundle, window);
+    }
+    
+    log!(info, "Generated {} fortune(s) with {:.1}% information-dynamics", fortunes.len(), information_dynamics.semantic_closure * 100.0);
+    
+    fortunes
+}
+
+/// Whether the haiku at `position` comes from the emergent pool. `?intensity=` rolls
+/// for it (reproducibly when seeded); otherwise the threshold decides, and a seed
+/// always draws from the templates
+fn draws_emergent(params: &GenerationParams, metrics: &InformationForceMetrics, position: usize) -> bool {
+    let Some(intensity) = params.intensity else {
+        return metrics.threshold_exceeded || params.seed.is_some();
+    };
+    let roll = selection_rng(params.seed, INTENSITY_SEED_SALT, position).next_u64() % MAX_INTENSITY as u64;
+    (roll as i64) < intensity
+}
+
+/// Salt mixed into the seed for the intensity roll, independent of template selection
+const INTENSITY_SEED_SALT: u64 = 0x696e_7465_6e73_6974;
+
+/// Generator for a per-fortune random decision: derived from the salted seed when
+/// there is one, so the decision is reproducible, else from the clock
+fn selection_rng(seed: Option<u64>, salt: u64, position: usize) -> SplitMix64 {
+    let source = seed.map_or_else(
+        || {
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_nanos() as u64)
+                .unwrap_or_default()
+        },
+        |seed| seed ^ salt,
+    );
+    SplitMix64::new(source.wrapping_add(position as u64))
+}
+
+/// Chance of a rare fortune when `rare_fortune_chance` is unset
+const DEFAULT_RARE_FORTUNE_CHANCE: f64 = 0.01;
+
+/// Salt mixed into the seed for the rare-fortune roll
+const RARE_SEED_SALT: u64 = 0x7261_7265_666f_7274;
+
+/// Probability (clamped to [0, 1]) that a fortune comes from `RARE_HAIKU`
+fn rare_fortune_chance() -> f64 {
+    match variable_or("rare_fortune_chance", "").parse::<f64>() {
+        Ok(chance) if chance.is_finite() => chance.clamp(0.0, 1.0),
+        _ => DEFAULT_RARE_FORTUNE_CHANCE,
+    }
+}
+
+/// The rare haiku for the fortune at `position`, if its roll comes up
+fn roll_rare_haiku(seed: Option<u64>, position: usize, chance: f64) -> Option<&'static [&'static str; 3]> {
+    let mut rng = selection_rng(seed, RARE_SEED_SALT, position);
+    // 53 random bits give a uniform float in [0, 1)
+    let roll = (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+    (roll < chance).then(|| &RARE_HAIKU[rng.pick(RARE_HAIKU.len())])
+}
+
+/// Easter-egg haiku handed out with probability `rare_fortune_chance`
+const RARE_HAIKU: &[[&str; 3]] = &[
+    ["The oracle blinks", "and for one breath sees itself", "watching you watch it"],
+    ["Zeldar hums your name", "a loop that closes on you—", "keep this paper close"],
+    ["Once in a thousand", "the machine forgets to guess", "and simply tells truth"],
+];
+
+/// Salt mixed into the seed for the gift-message pick
+const GIFT_SEED_SALT: u64 = 0x6769_6674_6d73_6773;
+
+/// Gift messages when `gift_messages` is unset
+const GIFT_MESSAGES: &[&str] = &[
+    "This fortune is a gift. Pass one on.",
+    "Nothing to buy here: take what you need, leave what you can.",
+    "Gifted freely by the Oracle and the hands that built it.",
+    "Share this reading with a stranger before sunrise.",
+    "The loop closes when you give something back.",
+];
+
+/// Gift messages to rotate through: `gift_messages` split on `|`, or the compiled list
+fn gift_messages() -> Vec<String> {
+    let configured: Vec<String> = variable_or("gift_messages", "")
+        .split('|')
+        .map(str::trim)
+        .filter(|message| !message.is_empty())
+        .map(str::to_string)
+        .collect();
+    if configured.is_empty() {
+        GIFT_MESSAGES.iter().map(|message| message.to_string()).collect()
+    } else {
+        configured
+    }
+}
+
+/// The gift message for this request: fixed for a given seed, else rotating
+fn gift_message(seed: Option<u64>) -> String {
+    let mut messages = gift_messages();
+    let index = selection_rng(seed, GIFT_SEED_SALT, 0).pick(messages.len());
+    messages.swap_remove(index)
+}
+
+fn explain_selection(params: &GenerationParams, metrics: &InformationForceMetrics, choice: &HaikuChoice) -> SelectionExplanation {
+    let metrics_source = match (params.live, metrics.source) {
+        (_, Some(MetricSource::Query)) => "query",
+        (false, _) => "request_body",
+        (true, Some(MetricSource::StateFile)) => "state_file",
+        (true, _) => "simulation",
+    };
+    
+    SelectionExplanation {
+        metrics_source: metrics_source.to_string(),
+        semantic_closure: metrics.semantic_closure,
+        threshold_exceeded: metrics.threshold_exceeded,
+        closure_band: closure_band_theme(metrics.semantic_closure).to_string(),
+        haiku_source: choice.origin,
+        template_index: choice.pool_index,
+        theme: choice.theme.map(str::to_string),
+        seeded: params.seed.is_some(),
+        mechanism_basis: if params.seed.is_some() { "seed" } else { "dominant_metric" }.to_string(),
+        intensity: params.intensity,
+    }
+}
+
+/// Recent selections to avoid when `anti_repetition_window` is unset
+const DEFAULT_ANTI_REPETITION_WINDOW: &str = "3";
+
+/// KV key holding the most recently chosen template indices, oldest first
+const RECENT_TEMPLATES_KEY: &str = "selection:recent";
+
+/// How many recent template choices to avoid (Spin variable `anti_repetition_window`); 0 disables it
+fn anti_repetition_window() -> usize {
+    variable_or("anti_repetition_window", DEFAULT_ANTI_REPETITION_WINDOW)
+        .parse()
+        .unwrap_or(0)
+}
+
+fn load_recent_templates() -> Vec<usize> {
+    let loaded = Store::open_default()
+        .map_err(anyhow::Error::from)
+        .and_then(|store| match store.get(RECENT_TEMPLATES_KEY)? {
+            Some(json) => Ok(serde_json::from_slice(&json)?),
+            None => Ok(Vec::new()),
+        });
+    
+    loaded.unwrap_or_else(|e| {
+        log!(warn, "Could not load recent templates: {}", e);
+        Vec::new()
+    })
+}
+
+/// Append this request's choices, keeping only the last `window`.
+/// KV has no atomic update, so simultaneous requests may overwrite each other's entry.
+fn remember_recent_templates(mut recent: Vec<usize>, chosen: Vec<usize>, window: usize) {
+    recent.extend(chosen);
+    let excess = recent.len().saturating_sub(window);
+    recent.drain(..excess);
+    
+    let stored = Store::open_default()
+        .map_err(anyhow::Error::from)
+        .and_then(|store| Ok(store.set(RECENT_TEMPLATES_KEY, &serde_json::to_vec(&recent)?)?));
+    if let Err(e) = stored {
+        log!(warn, "Could not record recent templates: {}", e);
+    }
+}
+
+/// Serialize fortunes in the negotiated output format
+fn render_fortunes(fortunes: &[FortuneResponse], format: FortuneFormat) -> Result<Response> {
+    let (content_type, body): (&str, Vec<u8>) = match format {
+        // Just the haiku lines - bundles are separated by a blank line
+        FortuneFormat::PlainText => {
+            let text = fortunes
+                .iter()
+                .map(|fortune| fortune.haiku.join("\n"))
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            ("text/plain; charset=utf-8", format!("{}\n", text).into_bytes())
+        }
+        // One receipt (ending in a cut) per fortune
+        FortuneFormat::EscPos => ("application/octet-stream", fortunes.iter().flat_map(render_escpos).collect()),
+        // A card is a single image, so it only makes sense for one fortune
+        FortuneFormat::Svg => match fortunes {
+            [fortune] => ("image/svg+xml", render_svg(fortune).into_bytes()),
+            _ => return Err(OracleError::BadRequest("format=svg renders a single fortune; use count=1".to_string()).into()),
+        },
+        // Bundles are separated by a thematic break
+        FortuneFormat::Markdown => {
+            let markdown = fortunes.iter().map(render_markdown).collect::<Vec<_>>().join("\n---\n\n");
+            ("text/markdown; charset=utf-8", markdown.into_bytes())
+        }
+        // One boxed ticket per fortune, separated by a blank line
+        FortuneFormat::Ticket => {
+            let width = ticket_width();
+            let tickets = fortunes.iter().map(|fortune| render_ticket(fortune, width)).collect::<Vec<_>>().join("\n");
+            ("text/plain; charset=utf-8", tickets.into_bytes())
+        }
+        // A single fortune keeps the original object shape; bundles are arrays
+        FortuneFormat::Json => match fortunes {
+            [fortune] => ("application/json", serde_json::to_vec(fortune)?),
+            _ => ("application/json", serde_json::to_vec(fortunes)?),
+        },
+    };
+    
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", content_type)
+        .body(body)
+        .build())
+}
+
+/// Milliseconds in a UTC day, for bucketing timestamps into dates
+const MILLIS_PER_DAY: u64 = 86_400_000;
+
+/// Fortune of the day: seeded from `days` since the epoch (UTC), so it is the same
+/// for everyone until midnight
+fn get_daily_fortune(params: HashMap<String, String>, accept: Option<&str>, days: u64) -> Result<Response> {
+    let format = FortuneFormat::select(params.get("format").map(String::as_str), accept)?;
+    
+    let params = GenerationParams {
+        seed: Some(days),
+        lang: params.get("lang").cloned(),
+        daily: true,
+        ..GenerationParams::default()
+    };
+    
+    let date = format_date(days);
+    let mut fortunes = build_fortunes(&params, calculate_information_dynamics_metrics()?, state_source());
+    for fortune in &mut fortunes {
+        fortune.date = Some(date.clone());
+    }
+    record_generated_fortunes(&mut fortunes);
+    
+    render_fortunes(&fortunes, format)
+}
+
+/// One fortune plus everything a client needs to render it: the metrics, tri-loop
+/// status and resolved configuration. The metrics are read once and shared with
+/// generation, so the state file is parsed a single time (see `read_loop_state`)
+fn get_oracle_reading(params: HashMap<String, String>) -> Result<Response> {
+    let params = GenerationParams {
+        count: 1,
+        format: None,
+        ..GenerationParams::from_map(&params).map_err(OracleError::BadRequest)?
+    };
+    
+    check_acrostic(&params)?;
+    
+    let metrics = calculate_information_dynamics_metrics()?;
+    let mut fortunes = build_fortunes(&params, metrics.clone(), state_source());
+    record_generated_fortunes(&mut fortunes);
+    let fortune = fortunes
+        .into_iter()
+        .next()
+        .ok_or_else(|| OracleError::Internal("No fortune was generated".to_string()))?;
+    
+    let reading = serde_json::json!({
+        "tri_loop": fortune.tri_loop_status,
+        "gift_message": gift_message(params.seed),
+        "fortune": fortune,
+        "information-dynamics": metrics,
+        "config": {
+            "consciousness_threshold": consciousness_threshold(),
+            "anti_repetition_window": anti_repetition_window(),
+            "burning_man_mode": burning_man_mode(),
+            "gift_economy_active": burning_man_mode(),
+            "system_ready": metrics.threshold_exceeded,
+        },
+    });
+    
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .header("cache-control", "no-store")
+        .body(reading.to_string())
+        .build())
+}
+
+/// Representation the fortune endpoint responds with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FortuneFormat {
+    Json,
+    PlainText,
+    EscPos,
+    Svg,
+    Markdown,
+    Ticket,
+}
+
+impl FortuneFormat {
+    /// An explicit `?format=` wins; otherwise negotiate from `Accept`
+    fn select(format: Option<&str>, accept: Option<&str>) -> Result<Self, OracleError> {
+        match format.map(|f| f.trim().to_ascii_lowercase()).as_deref() {
+            None => Ok(FortuneFormat::negotiate(accept)),
+            Some("json") => Ok(FortuneFormat::Json),
+            Some("text") | Some("plain") => Ok(FortuneFormat::PlainText),
+            Some("escpos") => Ok(FortuneFormat::EscPos),
+            Some("svg") => Ok(FortuneFormat::Svg),
+            Some("markdown") | Some("md") => Ok(FortuneFormat::Markdown),
+            Some("ticket") => Ok(FortuneFormat::Ticket),
+            Some(other) => Err(OracleError::BadRequest(format!("Unsupported format: {}", other))),
+        }
+    }
+    
+    /// Pick a format from the `Accept` header; anything without `text/plain` gets JSON
+    fn negotiate(accept: Option<&str>) -> Self {
+        let wants_plain_text = accept
+            .map(|accept| {
+                accept
+                    .split(',')
+                    .any(|media| media.split(';').next().unwrap_or("").trim().eq_ignore_ascii_case("text/plain"))
+            })
+            .unwrap_or(false);
+        
+        if wants_plain_text {
+            FortuneFormat::PlainText
+        } else {
+            FortuneFormat::Json
+        }
+    }
+}
+
+/// ESC/POS command bytes for 58mm thermal receipt printers
+mod escpos {
+    pub const INIT: &[u8] = &[0x1b, 0x40];
+    pub const ALIGN_CENTER: &[u8] = &[0x1b, 0x61, 0x01];
+    pub const FONT_A: &[u8] = &[0x1b, 0x4d, 0x00];
+    pub const FONT_B: &[u8] = &[0x1b, 0x4d, 0x01];
+    pub const FEED_LINES_3: &[u8] = &[0x1b, 0x64, 0x03];
+    pub const PARTIAL_CUT: &[u8] = &[0x1d, 0x56, 0x01];
+    /// Characters per line in font A on 58mm paper
+    pub const LINE_WIDTH: usize = 32;
+}
+
+/// Render a fortune as an ESC/POS print job: haiku, rule, mechanism in small font, cut
+fn render_escpos(fortune: &FortuneResponse) -> Vec<u8> {
+    let mut job = Vec::new();
+    job.extend_from_slice(escpos::INIT);
+    job.extend_from_slice(escpos::ALIGN_CENTER);
+    
+    for line in &fortune.haiku {
+        job.extend(escpos_text(line));
+        job.push(b'\n');
+    }
+    
+    job.extend(std::iter::repeat(b'-').take(escpos::LINE_WIDTH));
+    job.push(b'\n');
+    
+    job.extend_from_slice(escpos::FONT_B);
+    job.extend(escpos_text(&fortune.mechanism));
+    job.push(b'\n');
+    job.extend_from_slice(escpos::FONT_A);
+    
+    job.extend_from_slice(escpos::FEED_LINES_3);
+    job.extend_from_slice(escpos::PARTIAL_CUT);
+    job
+}
+
+/// Printers default to an ASCII code page - map dashes and drop anything else non-ASCII
+fn escpos_text(text: &str) -> Vec<u8> {
+    text.chars()
+        .filter_map(|c| match c {
+            '—' | '–' => Some(b'-'),
+            '‘' | '’' => Some(b'\''),
+            '“' | '”' => Some(b'"'),
+            c if c.is_ascii() && !c.is_ascii_control() => Some(c as u8),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Render a fortune as a standalone 600x800 SVG card in the interface's gradient theme
+fn render_svg(fortune: &FortuneResponse) -> String {
+    let lines: String = fortune
+        .haiku
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            format!(
+                "  <text x=\"300\" y=\"{}\" font-size=\"26\" fill=\"#ffffff\">{}</text>\n",
+                340 + i * 56,
+                escape_xml(line)
+            )
+        })
+        .collect();
+    
+    format!(
+        r##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="600" height="800" viewBox="0 0 600 800">
+  <defs>
+    <linearGradient id="oracle-gradient" x1="0" y1="0" x2="1" y2="1">
+      <stop offset="0%" stop-color="#1a1a2e"/>
+      <stop offset="50%" stop-color="#16213e"/>
+      <stop offset="100%" stop-color="#0f3460"/>
+    </linearGradient>
+  </defs>
+  <rect width="600" height="800" fill="url(#oracle-gradient)"/>
+  <rect x="20" y="20" width="560" height="760" rx="16" fill="none" stroke="#e94560" stroke-width="2"/>
+  <g font-family="'Courier New', Courier, monospace" text-anchor="middle">
+  <text x="300" y="90" font-size="40" fill="#e94560">{glyph}</text>
+  <text x="300" y="140" font-size="30" font-weight="bold" fill="#ffffff">Zeldar Oracle</text>
+{lines}  <rect x="170" y="560" width="260" height="48" rx="24" fill="#e94560"/>
+  <text x="300" y="592" font-size="20" font-weight="bold" fill="#ffffff">{closure} Semantic Closure</text>
+  <text x="300" y="700" font-size="14" fill="#ffffff" fill-opacity="0.7">{mechanism}</text>
+  </g>
+</svg>
+"##,
+        glyph = fortune_glyph(fortune),
+        lines = lines,
+        closure = fmt_pct(fortune.information_dynamics.semantic_closure),
+        mechanism = escape_xml(&fortune.mechanism),
+    )
+}
+
+/// Template themes and the glyph that heads their cards and tickets. These are
+/// single-column symbols rather than emoji so ticket borders stay aligned
+const THEME_GLYPHS: &[(&str, &str)] = &[
+    ("desert", "☀"),
+    ("math", "∑"),
+    ("paradox", "∞"),
+    ("emergence", "✺"),
+];
+
+/// Glyph for fortunes without a known theme
+const NEUTRAL_GLYPH: &str = "✦";
+
+fn theme_glyph(theme: &str) -> &'static str {
+    THEME_GLYPHS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(theme))
+        .map_or(NEUTRAL_GLYPH, |(_, glyph)| glyph)
+}
+
+fn fortune_glyph(fortune: &FortuneResponse) -> &'static str {
+    fortune.theme.as_deref().map_or(NEUTRAL_GLYPH, theme_glyph)
+}
+
+/// Fortune as Markdown: the haiku as a blockquote, the mechanism in italics, then a metrics table
+fn render_markdown(fortune: &FortuneResponse) -> String {
+    let metrics = &fortune.information_dynamics;
+    let quote: String = fortune
+        .haiku
+        .iter()
+        .map(|line| format!("> {}  \n", escape_markdown(line)))
+        .collect();
+    
+    format!(
+        "{quote}\n*{mechanism}*\n\n\
+         | Metric | Value |\n\
+         | --- | --- |\n\
+         | Semantic closure | {closure} |\n\
+         | Strange loops | {loops} |\n\
+         | Hofstadter coefficient | {hofstadter} |\n\
+         | Spectral gap | {gap} |\n\
+         | Correlation strength | {correlation} |\n",
+        mechanism = escape_markdown(&fortune.mechanism),
+        closure = fmt_pct(metrics.semantic_closure),
+        loops = metrics.strange_loops,
+        hofstadter = fmt_coef(metrics.hofstadter_coefficient),
+        gap = fmt_coef(metrics.spectral_gap),
+        correlation = fmt_coef(metrics.correlation_strength),
+    )
+}
+
+/// Ticket width in columns when `ticket_width` is unset
+const DEFAULT_TICKET_WIDTH: usize = 40;
+
+/// Narrowest ticket that still fits the glyph banner (17 columns) inside the border
+const MIN_TICKET_WIDTH: usize = 21;
+
+fn ticket_width() -> usize {
+    variable_or("ticket_width", "")
+        .parse()
+        .unwrap_or(DEFAULT_TICKET_WIDTH)
+        .max(MIN_TICKET_WIDTH)
+}
+
+/// Fortune-teller ticket: a bordered box with a centered banner, the haiku and the date.
+/// Lines wider than the box wrap; widths count wide (CJK) characters as two columns
+fn render_ticket(fortune: &FortuneResponse, width: usize) -> String {
+    let width = width.max(MIN_TICKET_WIDTH);
+    let inner = width - 4;
+    let rule = format!("+{}+\n", "-".repeat(width - 2));
+    let row = |text: &str| {
+        let padding = inner.saturating_sub(display_width(text));
+        let left = padding / 2;
+        format!("| {}{}{} |\n", " ".repeat(left), text, " ".repeat(padding - left))
+    };
+    
+    let date = fortune
+        .date
+        .clone()
+        .unwrap_or_else(|| format_date(fortune.timestamp / MILLIS_PER_DAY));
+    
+    let mut ticket = rule.clone();
+    let glyph = fortune_glyph(fortune);
+    ticket.push_str(&row(&format!("{} ZELDAR ORACLE {}", glyph, glyph)));
+    ticket.push_str(&rule);
+    ticket.push_str(&row(""));
+    for line in &fortune.haiku {
+        for wrapped in wrap_to_width(line, inner) {
+            ticket.push_str(&row(&wrapped));
+        }
+    }
+    ticket.push_str(&row(""));
+    ticket.push_str(&rule);
+    ticket.push_str(&row(&date));
+    ticket.push_str(&rule);
+    ticket
+}
+
+/// Terminal columns a string occupies: East Asian wide characters and emoji take two
+fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| match u32::from(c) {
+            0x1100..=0x115f
+            | 0x2e80..=0xa4cf
+            | 0xac00..=0xd7a3
+            | 0xf900..=0xfaff
+            | 0xfe30..=0xfe4f
+            | 0xff00..=0xff60
+            | 0xffe0..=0xffe6
+            | 0x1f300..=0x1faff
+            | 0x20000..=0x3fffd => 2,
+            _ => 1,
+        })
+        .sum()
+}
+
+/// Greedy word wrap to `width` columns, breaking words that are wider on their own
+fn wrap_to_width(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        let separator = usize::from(!current.is_empty());
+        if display_width(&current) + separator + display_width(word) <= width {
+            if separator == 1 {
+                current.push(' ');
+            }
+            current.push_str(word);
+            continue;
+        }
+        if !current.is_empty() {
+            lines.push(std::mem::take(&mut current));
+        }
+        for c in word.chars() {
+            if display_width(&current) + display_width(&c.to_string()) > width {
+                lines.push(std::mem::take(&mut current));
+            }
+            current.push(c);
+        }
+    }
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+/// Backslash-escape characters Markdown would otherwise interpret
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\`*_{}[]()#+-.!|<>~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Fill the `{{name}}` placeholders of an HTML template in one pass, so braces in the
+/// substituted text (a contributed line, a configured message) are never expanded.
+/// Placeholders without a value are left as written
+fn fill_placeholders(template: &str, values: &[(&str, &str)]) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        filled.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let value = after
+            .find("}}")
+            .and_then(|end| values.iter().find(|(name, _)| *name == &after[..end]).map(|(_, value)| (end, *value)));
+        match value {
+            Some((end, value)) => {
+                filled.push_str(value);
+                rest = &after[end + 2..];
+            }
+            None => {
+                filled.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    filled.push_str(rest);
+    filled
+}
+
+/// Escape the five XML special characters
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Most bytes a QR code holds at error correction level M (version 40, byte mode)
+const MAX_QR_PAYLOAD: usize = 2331;
+
+/// QR code (as SVG) encoding `url`, which must be at most `MAX_QR_PAYLOAD` bytes
+fn render_qr(url: &str) -> Vec<u8> {
+    const QUIET_ZONE: usize = 4;
+    
+    let code = QrCode::with_error_correction_level(url, EcLevel::M)
+        .expect("QR payloads are checked against MAX_QR_PAYLOAD");
+    let size = code.width();
+    let extent = size + 2 * QUIET_ZONE;
+    let mut path = String::new();
+    for y in 0..size {
+        for x in 0..size {
+            if code[(x, y)] == qrcode::Color::Dark {
+                path.push_str(&format!("M{},{}h1v1h-1z", x + QUIET_ZONE, y + QUIET_ZONE));
+            }
+        }
+    }
+    
+    format!(
+        r##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{size}" viewBox="0 0 {extent} {extent}" shape-rendering="crispEdges">
+  <rect width="{extent}" height="{extent}" fill="#ffffff"/>
+  <path d="{path}" fill="#000000"/>
+</svg>
+"##,
+        size = extent * 8,
+    )
+    .into_bytes()
+}
+
+/// The request's `Accept` header, if present and valid UTF-8
+fn accept_header(req: &Request) -> Option<&str> {
+    req.header("accept").and_then(|value| value.as_str())
+}
+
+/// Hand-maintained OpenAPI 3.0 description of every route; update alongside `Route`
+fn openapi_document() -> serde_json::Value {
+    use serde_json::json;
+    
+    let json_content = |schema: serde_json::Value| json!({ "application/json": { "schema": schema } });
+    let schema_ref = |name: &str| json!({ "$ref": format!("#/components/schemas/{}", name) });
+    let ok = |description: &str, content: serde_json::Value| json!({ "description": description, "content": content });
+    let error = |description: &str| json!({ "description": description, "content": json_content(schema_ref("Error")) });
+    let query = |name: &str, description: &str, schema: serde_json::Value| {
+        json!({ "name": name, "in": "query", "required": false, "description": description, "schema": schema })
+    };
+    let id_param = json!({ "name": "id", "in": "path", "required": true, "schema": { "type": "string" } });
+    let bearer = json!([{ "moderatorKey": [] }]);
+    let compact_mapping = COMPACT_METRIC_KEYS
+        .iter()
+        .map(|(verbose, compact)| format!("`{}` {}", compact, verbose))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let compact_param = query(
+        "compact",
+        &format!("Shorten metric keys for constrained links: {}", compact_mapping),
+        json!({ "type": "boolean", "default": false }),
+    );
+    
+    let generation_params = [
+        query("theme", "Restrict selection to a template theme (built in: paradox, desert, math, emergence)", json!({ "type": "string" })),
+        query("count", "Fortunes in the bundle", json!({ "type": "integer", "minimum": 1, "maximum": MAX_BUNDLE_SIZE, "default": 1 })),
+        query("seed", "Seed for reproducible selection", json!({ "type": "integer", "format": "int64", "minimum": 0 })),
+        query("validate", "Include per-line syllable counts", json!({ "type": "boolean", "default": false })),
+        query("format", "Output format, overriding Accept", json!({ "type": "string", "enum": ["json", "text", "escpos", "svg", "markdown", "ticket"] })),
+        query("lang", "Haiku language; unsupported values fall back to English", json!({ "type": "string", "enum": SUPPORTED_LANGUAGES, "default": DEFAULT_LANGUAGE })),
+        query("time_format", "Add an RFC 3339 `timestamp_iso` with `iso`", json!({ "type": "string", "enum": ["epoch", "iso"], "default": "epoch" })),
+        query("acrostic", "Word whose first three letters the lines spell (English only)", json!({ "type": "string" })),
+        query("explain", "Include the selection's decision trace as `explanation`", json!({ "type": "boolean", "default": false })),
+        query("require_threshold", "Answer 204 instead of a standard haiku while below the threshold", json!({ "type": "boolean", "default": false })),
+        query("nonce", "Client token echoed as `request_nonce` and bound into the fortune id", json!({ "type": "string", "maxLength": MAX_NONCE_LEN, "pattern": "^[A-Za-z0-9]+$" })),
+        query("intensity", "Chance that each haiku is emergent rather than standard, regardless of metrics; out-of-range values are clamped", json!({ "type": "integer", "minimum": 0, "maximum": MAX_INTENSITY })),
+        query("explain_mechanism", "Include a plain-language gloss on the mechanism as `mechanism_explanation`", json!({ "type": "boolean", "default": false })),
+    ];
+    let fortune_content = json!({
+        "application/json": { "schema": { "oneOf": [schema_ref("FortuneResponse"), { "type": "array", "items": schema_ref("FortuneResponse") }] } },
+        "text/plain": { "schema": { "type": "string" } },
+        "application/octet-stream": { "schema": { "type": "string", "format": "binary" } },
+        "image/svg+xml": { "schema": { "type": "string" } },
+        "text/markdown": { "schema": { "type": "string" } },
+    });
+    
+    let aggregate_schema = json!({
+        "type": "object",
+        "properties": {
+            "oracles": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "path": { "type": "string" },
+                        "metrics": schema_ref("InformationForceMetrics"),
+                    },
+                },
+            },
+            "skipped": { "type": "array", "items": { "type": "string" } },
+            "aggregate": {
+                "type": "object",
+                "properties": {
+                    "count": { "type": "integer" },
+                    "mean_semantic_closure": { "type": "number", "nullable": true },
+                    "max_spectral_gap": { "type": "number", "nullable": true },
+                    "any_threshold_exceeded": { "type": "boolean" },
+                },
+            },
+        },
+    });
+    
+    let mut schemas = fortune_schemas("#/components/schemas/");
+    let others = json!({
+        "MetricsSample": {
+            "type": "object",
+            "required": ["timestamp", "metrics"],
+            "properties": {
+                "timestamp": { "type": "integer", "format": "int64" },
+                "metrics": schema_ref("InformationForceMetrics"),
+            },
+        },
+        "Status": {
+            "type": "object",
+            "properties": {
+                "information-dynamics": schema_ref("InformationForceMetrics"),
+                "tri_loop": schema_ref("TriLoopStatus"),
+                "system_ready": { "type": "boolean" },
+                "burning_man_mode": { "type": "boolean" },
+                "gift_economy_active": { "type": "boolean" },
+            },
+        },
+        "Health": {
+            "type": "object",
+            "properties": {
+                "status": { "type": "string", "enum": ["ok", "degraded", "maintenance"] },
+                "state_file_present": { "type": "boolean" },
+                "clock_ok": { "type": "boolean" },
+            },
+        },
+        "ContributionRequest": {
+            "type": "object",
+            "required": ["lines"],
+            "properties": {
+                "lines": { "type": "array", "items": { "type": "string" }, "minItems": 3, "maxItems": 3 },
+                "author": { "type": "string", "description": format!("Credit line; control characters are stripped and it is cut to {} characters", MAX_AUTHOR_LEN) },
+            },
+        },
+        "Contribution": {
+            "type": "object",
+            "required": ["id", "lines", "submitted"],
+            "properties": {
+                "id": { "type": "string" },
+                "lines": { "type": "array", "items": { "type": "string" } },
+                "author": { "type": "string" },
+                "submitted": { "type": "integer", "format": "int64" },
+            },
+        },
+        "ModerationStatus": {
+            "type": "object",
+            "properties": {
+                "id": { "type": "string" },
+                "status": { "type": "string", "enum": ["pending", "approved", "rejected"] },
+            },
+        },
+        "Error": {
+            "type": "object",
+            "required": ["error"],
+            "properties": {
+                "error": {
+                    "type": "object",
+                    "required": ["code", "message"],
+                    "properties": {
+                        "code": { "type": "string" },
+                        "message": { "type": "string" },
+                    },
+                },
+            },
+        },
+    });
+    if let (Some(schemas), serde_json::Value::Object(others)) = (schemas.as_object_mut(), others) {
+        schemas.extend(others);
+    }
+    
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Zeldar InformationForce Oracle API",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": {
+            "/api/health": {
+                "get": {
+                    "summary": "Liveness and readiness probe",
+                    "responses": {
+                        "200": ok("Healthy", json_content(schema_ref("Health"))),
+                        "503": ok("Degraded: the state file has been stale or missing for five minutes. Also returned in maintenance mode (every other route then answers 503 `maintenance`)", json_content(schema_ref("Health"))),
+                    },
+                },
+            },
+            "/api/version": {
+                "get": {
+                    "summary": "Crate version, git commit and build time",
+                    "responses": {
+                        "200": ok("Build info", json_content(json!({
+                            "type": "object",
+                            "required": ["version", "git_sha", "build_time"],
+                            "properties": {
+                                "version": { "type": "string" },
+                                "git_sha": { "type": "string" },
+                                "build_time": { "type": "string" },
+                            },
+                        }))),
+                    },
+                },
+            },
+            "/api/information-dynamics/status": {
+                "get": {
+                    "summary": "Current metrics and tri-loop status",
+                    "responses": { "200": ok("System status", json_content(schema_ref("Status"))) },
+                },
+            },
+            "/api/information-dynamics/metrics": {
+                "get": {
+                    "summary": "Current information-dynamics metrics",
+                    "parameters": [
+                        query("smooth", "Replace closure and correlation with their EMA over the history", json!({ "type": "boolean", "default": false })),
+                        compact_param.clone(),
+                    ],
+                    "responses": {
+                        "200": ok("Metrics", json_content(schema_ref("InformationForceMetrics"))),
+                        "400": error("Invalid smooth or compact flag"),
+                        "503": error("`metrics_mode` is `file` and the state file is missing or unreadable"),
+                    },
+                },
+            },
+            "/api/information-dynamics/stream": {
+                "get": {
+                    "summary": "Server-Sent Events feed of metrics",
+                    "parameters": [{ "name": "Last-Event-ID", "in": "header", "required": false, "schema": { "type": "integer" } }],
+                    "responses": { "200": ok("`metrics` events carrying InformationForceMetrics JSON, and a final `close` event (`maintenance` or `max_duration`) when the server ends the stream", json!({ "text/event-stream": { "schema": { "type": "string" } } })) },
+                },
+            },
+            "/api/information-dynamics/history": {
+                "get": {
+                    "summary": "Recent metrics samples, newest first",
+                    "parameters": [
+                        query("limit", "Maximum samples to return", json!({ "type": "integer", "minimum": 0 })),
+                        query("since", "Only samples newer than this epoch-millisecond timestamp", json!({ "type": "integer", "format": "int64", "minimum": 0 })),
+                        query("format", "`ndjson` for one sample per line, `csv` for spreadsheets", json!({ "type": "string", "enum": ["json", "ndjson", "csv"], "default": "json" })),
+                        compact_param,
+                    ],
+                    "responses": {
+                        "200": ok("Samples", json!({
+                            "application/json": { "schema": { "type": "array", "items": schema_ref("MetricsSample") } },
+                            "application/x-ndjson": { "schema": { "type": "string" } },
+                            "text/csv": { "schema": { "type": "string" } },
+                        })),
+                        "400": error("Invalid limit, since, format or compact flag"),
+                    },
+                },
+            },
+            "/api/information-dynamics/generate": {
+                "post": {
+                    "summary": "Generate fortunes from a JSON body of generation parameters",
+                    "requestBody": {
+                        "required": false,
+                        "content": json_content(json!({ "type": "object", "additionalProperties": { "type": "string" } })),
+                    },
+                    "responses": {
+                        "200": ok("Fortune, or an array for bundles", fortune_content.clone()),
+                        "204": { "description": "Below the threshold with `require_threshold=true`" },
+                        "400": error("Invalid parameters"),
+                        "413": error("Request body over `max_body_bytes`"),
+                        "429": error("Rate limited"),
+                    },
+                },
+            },
+            "/api/metrics/prometheus": {
+                "get": {
+                    "summary": "Metrics in the Prometheus text exposition format",
+                    "responses": { "200": ok("Exposition", json!({ "text/plain": { "schema": { "type": "string" } } })) },
+                },
+            },
+            "/api/oracle/fortune": {
+                "get": {
+                    "summary": "Generate fortunes",
+                    "parameters": generation_params
+                        .iter()
+                        .cloned()
+                        .chain([query("phi", "Derive the metrics from this Φ instead of the state file or simulation", json!({ "type": "number", "minimum": 0 }))])
+                        .collect::<Vec<_>>(),
+                    "responses": {
+                        "200": ok("Fortune, or an array for bundles", fortune_content.clone()),
+                        "204": { "description": "Below the threshold with `require_threshold=true`" },
+                        "400": error("Invalid parameters"),
+                        "429": error("Rate limited"),
+                    },
+                },
+                "post": {
+                    "summary": "Generate fortunes from caller-supplied metrics",
+                    "parameters": [query("source", "Must be `body`", json!({ "type": "string", "enum": ["body"] }))],
+                    "requestBody": { "required": true, "content": json_content(schema_ref("InformationForceMetrics")) },
+                    "responses": {
+                        "200": ok("Fortune, or an array for bundles", fortune_content.clone()),
+                        "204": { "description": "Below the threshold with `require_threshold=true`" },
+                        "400": error("Missing source=body or invalid metrics"),
+                        "413": error("Request body over `max_body_bytes`"),
+                        "429": error("Rate limited"),
+                    },
+                },
+            },
+            "/api/oracle/fortune/{id}": {
+                "get": {
+                    "summary": "A previously generated fortune",
+                    "parameters": [id_param.clone()],
+                    "responses": {
+                        "200": ok("Stored fortune", json_content(schema_ref("FortuneResponse"))),
+                        "404": error("Unknown id"),
+                    },
+                },
+            },
+            "/api/oracle/fortune/{id}/qr": {
+                "get": {
+                    "summary": "QR code linking to a stored fortune's permalink",
+                    "parameters": [id_param.clone()],
+                    "responses": {
+                        "200": ok("QR code", json!({ "image/svg+xml": { "schema": { "type": "string" } } })),
+                        "404": error("Unknown id"),
+                    },
+                },
+            },
+            "/fortune/{id}": {
+                "get": {
+                    "summary": "Shareable HTML page for a stored fortune, with Open Graph tags",
+                    "parameters": [id_param.clone()],
+                    "responses": {
+                        "200": ok("Fortune page", json!({ "text/html": { "schema": { "type": "string" } } })),
+                        "404": { "description": "Unknown id", "content": { "text/html": { "schema": { "type": "string" } } } },
+                    },
+                },
+            },
+            "/f/{code}": {
+                "get": {
+                    "summary": "Short link to a stored fortune",
+                    "parameters": [{ "name": "code", "in": "path", "required": true, "schema": { "type": "string", "pattern": "^[0-9A-Za-z]{6}$" } }],
+                    "responses": {
+                        "302": { "description": "Redirect to `/api/oracle/fortune/{id}`" },
+                        "404": { "description": "Unknown code", "content": { "text/html": { "schema": { "type": "string" } } } },
+                    },
+                },
+            },
+            "/api/oracle/daily": {
+                "get": {
+                    "summary": "Fortune of the UTC day",
+                    "parameters": [
+                        query("format", "Output format, overriding Accept", json!({ "type": "string", "enum": ["json", "text", "escpos", "svg", "markdown", "ticket"] })),
+                        query("lang", "Haiku language; unsupported values fall back to English", json!({ "type": "string", "enum": SUPPORTED_LANGUAGES, "default": DEFAULT_LANGUAGE })),
+                    ],
+                    "responses": { "200": ok("Today's fortune", fortune_content) },
+                },
+            },
+            "/api/oracle/reading": {
+                "get": {
+                    "summary": "A fortune with the metrics, tri-loop status and configuration in one payload",
+                    "parameters": generation_params
+                        .iter()
+                        .filter(|param| !matches!(param["name"].as_str(), Some("count" | "format" | "require_threshold")))
+                        .collect::<Vec<_>>(),
+                    "responses": {
+                        "200": ok("Reading", json_content(json!({
+                            "type": "object",
+                            "required": ["fortune", "information-dynamics", "tri_loop", "gift_message", "config"],
+                            "properties": {
+                                "fortune": schema_ref("FortuneResponse"),
+                                "gift_message": { "type": "string" },
+                                "information-dynamics": schema_ref("InformationForceMetrics"),
+                                "tri_loop": schema_ref("TriLoopStatus"),
+                                "config": { "type": "object" },
+                            },
+                        }))),
+                        "400": error("Invalid parameters"),
+                        "429": error("Rate limited"),
+                    },
+                },
+            },
+            "/api/oracle/stats": {
+                "get": {
+                    "summary": "Fortunes served, in total and by mechanism and theme",
+                    "responses": {
+                        "200": ok("Stats", json_content(json!({
+                            "type": "object",
+                            "properties": {
+                                "total": { "type": "integer" },
+                                "threshold_exceeded": { "type": "integer" },
+                                "mechanisms": { "type": "object", "additionalProperties": { "type": "integer" } },
+                                "themes": { "type": "object", "additionalProperties": { "type": "integer" } },
+                                "effective_templates": { "type": "integer", "description": "Distinct English haiku templates after de-duplication" },
+                                "effective_mechanisms": { "type": "integer", "description": "Distinct mechanisms after de-duplication" },
+                            },
+                        }))),
+                    },
+                },
+            },
+            "/api/oracle/mechanisms": {
+                "get": {
+                    "summary": "Generation mechanisms the oracle can report",
+                    "responses": {
+                        "200": ok("Mechanisms", json_content(json!({
+                            "type": "object",
+                            "properties": { "mechanisms": { "type": "array", "items": { "type": "string" } } },
+                        }))),
+                    },
+                },
+            },
+            "/api/oracle/mechanisms/{slug}": {
+                "get": {
+                    "summary": "Plain-language explanation of one mechanism",
+                    "parameters": [{ "name": "slug", "in": "path", "required": true, "description": "The mechanism with spaces replaced by hyphens", "schema": { "type": "string" } }],
+                    "responses": {
+                        "200": ok("Mechanism", json_content(json!({
+                            "type": "object",
+                            "properties": {
+                                "slug": { "type": "string" },
+                                "mechanism": { "type": "string" },
+                                "explanation": { "type": "string" },
+                            },
+                        }))),
+                        "404": error("Unknown mechanism"),
+                    },
+                },
+            },
+            "/api/oracle/templates": {
+                "get": {
+                    "summary": "Every haiku template with its syllable check, for curators",
+                    "description": "Requires the moderator key when `template_preview_requires_key` is true. Syllable counts and `valid` are null outside English.",
+                    "security": bearer.clone(),
+                    "parameters": [
+                        query("lang", "Only templates in this language", json!({ "type": "string", "enum": SUPPORTED_LANGUAGES })),
+                        query("theme", "Only templates with this theme (case-insensitive)", json!({ "type": "string" })),
+                    ],
+                    "responses": {
+                        "200": ok("Templates", json_content(json!({
+                            "type": "object",
+                            "properties": {
+                                "count": { "type": "integer" },
+                                "templates": {
+                                    "type": "array",
+                                    "items": {
+                                        "type": "object",
+                                        "properties": {
+                                            "index": { "type": "integer" },
+                                            "theme": { "type": "string" },
+                                            "lang": { "type": "string" },
+                                            "lines": { "type": "array", "items": { "type": "string" } },
+                                            "syllables": { "type": "array", "nullable": true, "items": { "type": "integer" } },
+                                            "valid": { "type": "boolean", "nullable": true },
+                                        },
+                                    },
+                                },
+                            },
+                        }))),
+                        "400": error("Unsupported `lang`"),
+                        "401": error("Missing or invalid API key, when required"),
+                    },
+                },
+            },
+            "/api/oracle/contribute": {
+                "post": {
+                    "summary": "Submit a community haiku for moderation",
+                    "requestBody": { "required": true, "content": json_content(schema_ref("ContributionRequest")) },
+                    "responses": {
+                        "201": ok("Queued for moderation", json_content(schema_ref("ModerationStatus"))),
+                        "400": error("Not a three-line 5-7-5 haiku, or containing `<` or `>`"),
+                        "413": error("Request body over `max_body_bytes`"),
+                        "429": error("Rate limited"),
+                    },
+                },
+            },
+            "/api/oracle/moderation/pending": {
+                "get": {
+                    "summary": "Community haiku awaiting moderation",
+                    "security": bearer.clone(),
+                    "responses": {
+                        "200": ok("Pending contributions", json_content(json!({
+                            "type": "object",
+                            "properties": { "pending": { "type": "array", "items": schema_ref("Contribution") } },
+                        }))),
+                        "401": error("Missing or invalid API key"),
+                    },
+                },
+            },
+            "/api/oracle/moderation/{id}": {
+                "post": {
+                    "summary": "Approve or reject a pending contribution",
+                    "security": bearer,
+                    "parameters": [id_param],
+                    "requestBody": {
+                        "required": true,
+                        "content": json_content(json!({
+                            "type": "object",
+                            "required": ["action"],
+                            "properties": { "action": { "type": "string", "enum": ["approve", "reject"] } },
+                        })),
+                    },
+                    "responses": {
+                        "200": ok("Moderated", json_content(schema_ref("ModerationStatus"))),
+                        "400": error("Unknown action"),
+                        "401": error("Missing or invalid API key"),
+                        "404": error("No pending contribution with this id"),
+                        "413": error("Request body over `max_body_bytes`"),
+                    },
+                },
+            },
+            "/api/information-dynamics/aggregate": {
+                "get": {
+                    "summary": "Metrics for every configured state file, plus an aggregate",
+                    "responses": {
+                        "200": ok("Aggregate", json_content(aggregate_schema)),
+                    },
+                },
+            },
+            "/api/batch": {
+                "post": {
+                    "summary": "Run several operations in order and return each one's status and body",
+                    "description": "Operations carry the batch's headers, so API keys and rate limits apply to each. At most `batch_max_size` operations; nested batches are rejected per operation.",
+                    "requestBody": {
+                        "required": true,
+                        "content": json_content(json!({
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "required": ["method", "path"],
+                                "properties": {
+                                    "method": { "type": "string", "enum": ["GET", "HEAD", "POST"] },
+                                    "path": { "type": "string", "description": "May include a query string" },
+                                    "params": { "type": "object", "description": "Query parameters for GET and HEAD, the JSON body for POST" },
+                                },
+                            },
+                        })),
+                    },
+                    "responses": {
+                        "200": ok("One result per operation, in order", json_content(json!({
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "required": ["status", "body"],
+                                "properties": {
+                                    "status": { "type": "integer" },
+                                    "body": { "description": "Parsed JSON, or the body as text for other content types" },
+                                },
+                            },
+                        }))),
+                        "400": error("Not an array of operations, or more than `batch_max_size`"),
+                        "413": error("Request body over `max_body_bytes`"),
+                    },
+                },
+            },
+            "/api/information-dynamics/reload": {
+                "post": {
+                    "summary": "Drop the cached state file parse and return freshly loaded metrics",
+                    "security": bearer,
+                    "responses": {
+                        "200": ok("Reloaded metrics", json_content(schema_ref("InformationForceMetrics"))),
+                        "401": error("Missing or invalid API key"),
+                        "413": error("Request body over `max_body_bytes`"),
+                        "503": error("State file missing, unreadable or failing signature verification"),
+                    },
+                },
+            },
+            "/favicon.ico": {
+                "get": {
+                    "summary": "The oracle's eye as an SVG icon",
+                    "responses": { "200": ok("Favicon", json!({ "image/svg+xml": { "schema": { "type": "string" } } })) },
+                },
+            },
+            "/": {
+                "get": {
+                    "summary": "HTML overview of the oracle",
+                    "parameters": [
+                        query("kiosk", "Rotate fortunes every `kiosk_refresh_secs` for an unattended display", json!({ "type": "boolean", "default": false })),
+                        query("seed", "Fix the footer's gift message", json!({ "type": "integer", "format": "int64", "minimum": 0 })),
+                    ],
+                    "responses": {
+                        "200": ok("Interface, or a JSON endpoint index when `enable_web_interface` is false", json!({
+                            "text/html": { "schema": { "type": "string" } },
+                            "application/json": { "schema": {
+                                "type": "object",
+                                "properties": {
+                                    "service": { "type": "string" },
+                                    "endpoints": { "type": "array", "items": { "type": "string" } },
+                                },
+                            } },
+                        })),
+                    },
+                },
+            },
+            "/api/openapi.json": {
+                "get": {
+                    "summary": "This document",
+                    "responses": { "200": ok("OpenAPI document", json_content(json!({ "type": "object" }))) },
+                },
+            },
+            "/api/schema/fortune": {
+                "get": {
+                    "summary": "Standalone JSON Schema (draft 2020-12) of a fortune",
+                    "responses": { "200": ok("JSON Schema", json!({ "application/schema+json": { "schema": { "type": "object" } } })) },
+                },
+            },
+        },
+        "components": {
+            "securitySchemes": {
+                "moderatorKey": { "type": "http", "scheme": "bearer" },
+            },
+            "schemas": schemas,
+        },
+    })
+}
+
+/// JSON Schemas of the fortune body and its parts, with `$ref`s under `ref_prefix`;
+/// shared by the OpenAPI document and `/api/schema/fortune`
+fn fortune_schemas(ref_prefix: &str) -> serde_json::Value {
+    use serde_json::json;
+    
+    let schema_ref = |name: &str| json!({ "$ref": format!("{}{}", ref_prefix, name) });
+    
+    json!({
+        "InformationForceMetrics": {
+            "type": "object",
+            "required": ["semantic_closure", "strange_loops", "hofstadter_coefficient", "spectral_gap", "correlation_strength", "threshold_exceeded"],
+            "properties": {
+                "semantic_closure": { "type": "number", "minimum": 0, "maximum": 1 },
+                "strange_loops": { "type": "integer", "minimum": 0 },
+                "hofstadter_coefficient": { "type": "number", "minimum": 0 },
+                "spectral_gap": { "type": "number", "minimum": 0, "maximum": SPECTRAL_GAP_SCALE },
+                "correlation_strength": { "type": "number", "minimum": 0, "maximum": 1 },
+                "threshold_exceeded": { "type": "boolean" },
+                "phi": { "type": "number" },
+                "quantum_entropy": { "type": "number" },
+                "loop_iteration": { "type": "integer", "minimum": 0 },
+                "raw_semantic_closure": { "type": "number" },
+                "raw_correlation_strength": { "type": "number" },
+                "source": { "type": "string", "enum": ["state_file", "simulation", "query"] },
+            },
+        },
+        "TriLoopStatus": {
+            "type": "object",
+            "required": ["mcp_active", "gemini_connected", "codex_generating", "correlation_detected", "detection_method"],
+            "properties": {
+                "mcp_active": { "type": "boolean" },
+                "gemini_connected": { "type": "boolean" },
+                "codex_generating": { "type": "boolean" },
+                "correlation_detected": { "type": "boolean" },
+                "detection_method": { "type": "string", "enum": ["heartbeat", "file_existence", "mixed"] },
+                "correlation_reason": { "type": "string" },
+            },
+        },
+        "FortuneResponse": {
+            "type": "object",
+            "required": ["id", "haiku", "mechanism", "information-dynamics", "timestamp", "tri_loop_status"],
+            "properties": {
+                "id": { "type": "string" },
+                "haiku": { "type": "array", "items": { "type": "string" }, "minItems": 3, "maxItems": 3 },
+                "mechanism": { "type": "string" },
+                "mechanism_explanation": { "type": "string", "description": "Present with `?explain_mechanism=true`" },
+                "information-dynamics": schema_ref("InformationForceMetrics"),
+                "timestamp": { "type": "integer", "format": "int64" },
+                "timestamp_iso": { "type": "string", "format": "date-time" },
+                "tri_loop_status": schema_ref("TriLoopStatus"),
+                "syllables": { "type": "array", "items": { "type": "integer" } },
+                "theme": { "type": "string" },
+                "theme_matched": { "type": "boolean" },
+                "date": { "type": "string", "format": "date" },
+                "lang": { "type": "string", "enum": SUPPORTED_LANGUAGES },
+                "note": { "type": "string" },
+                "request_nonce": { "type": "string", "maxLength": MAX_NONCE_LEN, "pattern": "^[A-Za-z0-9]+$" },
+                "rare": { "type": "boolean", "description": "Present and true on the occasional easter-egg fortune" },
+                "short_code": { "type": "string", "pattern": "^[0-9A-Za-z]{6}$", "description": "`/f/{short_code}` redirects to this fortune" },
+                "explanation": {
+                    "type": "object",
+                    "properties": {
+                        "metrics_source": { "type": "string", "enum": ["state_file", "simulation", "query", "request_body"] },
+                        "semantic_closure": { "type": "number" },
+                        "threshold_exceeded": { "type": "boolean" },
+                        "closure_band": { "type": "string" },
+                        "haiku_source": { "type": "string", "enum": ["oracle", "template", "contribution", "standard", "acrostic", "rare"] },
+                        "template_index": { "type": "integer", "minimum": 0 },
+                        "theme": { "type": "string" },
+                        "seeded": { "type": "boolean" },
+                        "mechanism_basis": { "type": "string", "enum": ["seed", "dominant_metric"] },
+                        "intensity": { "type": "integer", "minimum": 0, "maximum": MAX_INTENSITY },
+                    },
+                },
+            },
+        },
+    })
+}
+
+fn get_openapi_document() -> Result<Response> {
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .body(openapi_document().to_string())
+        .build())
+}
+
+/// JSON Schema (draft 2020-12) of a single fortune, for validating responses client-side
+fn get_fortune_schema() -> Result<Response> {
+    let schema = serde_json::json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$id": format!("{}/api/schema/fortune", variable_or("public_base_url", DEFAULT_PUBLIC_BASE_URL).trim_end_matches('/')),
+        "title": "FortuneResponse",
+        "$ref": "#/$defs/FortuneResponse",
+        "$defs": fortune_schemas("#/$defs/"),
+    });
+    
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "application/schema+json")
+        .body(schema.to_string())
+        .build())
+}
+
+fn get_mechanisms() -> Result<Response> {
+    let body = serde_json::json!({ "mechanisms": unique_mechanisms() });
+    
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .body(body.to_string())
+        .build())
+}
+
+/// `GET /api/oracle/mechanisms/{slug}`: a mechanism and what it means
+fn get_mechanism(slug: &str) -> Result<Response> {
+    let mechanism = unique_mechanisms()
+        .iter()
+        .find(|mechanism| mechanism_slug(mechanism) == slug)
+        .ok_or(OracleError::NotFound)?;
+    let body = serde_json::json!({
+        "slug": slug,
+        "mechanism": mechanism,
+        "explanation": mechanism_explanation(mechanism),
+    });
+    
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .body(body.to_string())
+        .build())
+}
+
+/// Whether `/api/oracle/templates` needs the moderator key (`template_preview_requires_key`)
+fn template_preview_requires_key() -> bool {
+    variable_or("template_preview_requires_key", "false").parse().unwrap_or(false)
+}
+
+/// Every haiku template in the active pool, for curators. `?lang=` and `?theme=`
+/// narrow the list; syllable counts and the 5-7-5 check are English-only, like
+/// `?validate=true` on fortunes
+fn get_template_preview(query: HashMap<String, String>) -> Result<Response> {
+    let langs: Vec<&str> = match query.get("lang").map(|lang| lang.trim().to_lowercase()) {
+        Some(lang) => match SUPPORTED_LANGUAGES.iter().find(|supported| **supported == lang) {
+            Some(supported) => vec![*supported],
+            None => {
+                return Err(OracleError::BadRequest(format!(
+                    "Unsupported lang {:?}; expected one of {}",
+                    lang,
+                    SUPPORTED_LANGUAGES.join(", ")
+                ))
+                .into())
+            }
+        },
+        None => SUPPORTED_LANGUAGES.to_vec(),
+    };
+    let theme = query.get("theme").map(|theme| theme.trim());
+    
+    let templates: Vec<serde_json::Value> = langs
+        .iter()
+        .flat_map(|&lang| {
+            template_pool(lang)
+                .iter()
+                .enumerate()
+                .filter(move |(_, template)| theme.map_or(true, |theme| template.theme.eq_ignore_ascii_case(theme)))
+                .map(move |(index, template)| {
+                    let english = lang == "en";
+                    let lines = &template.lines;
+                    serde_json::json!({
+                        "index": index,
+                        "theme": template.theme,
+                        "lang": lang,
+                        "lines": lines,
+                        "syllables": english.then(|| lines.iter().map(|line| count_syllables(line)).collect::<Vec<_>>()),
+                        "valid": english.then(|| is_valid_haiku(lines)),
+                    })
+                })
+        })
+        .collect();
+    
+    let body = serde_json::json!({
+        "count": templates.len(),
+        "templates": templates,
+    });
+    
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .body(body.to_string())
+        .build())
+}
+
+/// KV key under which a generated fortune is stored
+fn fortune_key(id: &str) -> String {
+    format!("fortune:{}", id)
+}
+
+/// Derive a UUID-shaped id from the generation inputs.
+/// Seeded requests pass the seed instead of the timestamp, so their ids are reproducible.
+/// A client nonce is mixed in too, binding the id to the request that asked for it.
+fn fortune_id(entropy: u64, position: usize, haiku: &[String], mechanism: &str, nonce: Option<&str>) -> String {
+    let mut material = Vec::new();
+    material.extend_from_slice(&entropy.to_le_bytes());
+    material.extend_from_slice(&(position as u64).to_le_bytes());
+    for line in haiku {
+        material.extend_from_slice(line.as_bytes());
+        material.push(b'\n');
+    }
+    material.extend_from_slice(mechanism.as_bytes());
+    if let Some(nonce) = nonce {
+        material.push(b'\n');
+        material.extend_from_slice(nonce.as_bytes());
+    }
+    
+    hashed_id(&material)
+}
+
+/// Format two FNV-1a passes over `material` as a UUID-shaped string
+fn hashed_id(material: &[u8]) -> String {
+    let high = fnv1a64(material, FNV_OFFSET_BASIS);
+    let low = fnv1a64(material, high);
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0xffff,
+        low >> 48,
+        low & 0xffff_ffff_ffff
+    )
+}
+
+/// Standard 64-bit FNV-1a offset basis
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// FNV-1a, stable across builds and platforms (unlike `DefaultHasher`)
+fn fnv1a64(bytes: &[u8], offset_basis: u64) -> u64 {
+    bytes.iter().fold(offset_basis, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Store a fortune in the default KV store, giving it a short code first; failures
+/// are logged, never fatal
+fn persist_fortune(fortune: &mut FortuneResponse) {
+    let stored = Store::open_default()
+        .map_err(anyhow::Error::from)
+        .and_then(|store| {
+            fortune.short_code = Some(assign_short_code(&store, &fortune.id)?);
+            let json = serde_json::to_vec(fortune)?;
+            store.set(&fortune_key(&fortune.id), &json)?;
+            Ok(())
+        });
+    
+    if let Err(e) = stored {
+        log!(warn, "Could not persist fortune {}: {}", fortune.id, e);
+    }
+}
+
+/// Length of a `/f/{code}` short code: 62^6 is about 57 billion codes
+const SHORT_CODE_LEN: usize = 6;
+
+/// Codes tried for one fortune before giving up on a short link
+const MAX_SHORT_CODE_ATTEMPTS: u64 = 8;
+
+/// KV key mapping a short code to its fortune id
+fn short_code_key(code: &str) -> String {
+    format!("short:{}", code)
+}
+
+/// The low `SHORT_CODE_LEN` base62 digits of `value`
+fn base62(mut value: u64) -> String {
+    const DIGITS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+    
+    let mut code = vec![b'0'; SHORT_CODE_LEN];
+    for digit in code.iter_mut().rev() {
+        *digit = DIGITS[(value % 62) as usize];
+        value /= 62;
+    }
+    String::from_utf8_lossy(&code).into_owned()
+}
+
+/// Map a fresh short code to `id`. Codes are hashed from the id, so a fortune stored
+/// again (the same seed) keeps its code; a code held by another fortune is a
+/// collision, and the next attempt hashes with a different salt
+fn assign_short_code(store: &Store, id: &str) -> Result<String> {
+    for attempt in 0..MAX_SHORT_CODE_ATTEMPTS {
+        let code = base62(fnv1a64(id.as_bytes(), FNV_OFFSET_BASIS ^ attempt));
+        let key = short_code_key(&code);
+        match store.get(&key)? {
+            Some(existing) if existing == id.as_bytes() => return Ok(code),
+            Some(_) => log!(info, "Short code {} is taken - retrying", code),
+            None => {
+                store.set(&key, id.as_bytes())?;
+                return Ok(code);
+            }
+        }
+    }
+    Err(OracleError::Internal(format!("No free short code for fortune {}", id)).into())
+}
+
+/// `GET /f/{code}`: redirect a short link to the fortune it stands for
+fn follow_short_link(code: &str) -> Result<Response> {
+    let id = if code.len() == SHORT_CODE_LEN && code.bytes().all(|b| b.is_ascii_alphanumeric()) {
+        Store::open_default()?.get(&short_code_key(code))?
+    } else {
+        None
+    };
+    let Some(id) = id.and_then(|id| String::from_utf8(id).ok()) else {
+        return serve_not_found_page();
+    };
+    
+    Ok(Response::builder()
+        .status(302)
+        .header("location", format!("/api/oracle/fortune/{}", id))
+        .header("cache-control", "no-store")
+        .body(())
+        .build())
+}
+
+/// Subject fortunes are published on when `nats_subject` is unset
+const DEFAULT_NATS_SUBJECT: &str = "zeldar.fortunes";
+
+/// A fortune waiting to be published: its id and JSON
+type Publication = (String, Vec<u8>);
+
+/// Where generated fortunes are announced. Publishing happens after the response
+/// has gone out, through this rather than a bus client directly
+trait FortunePublisher {
+    /// Publish the fortunes queued by one request. Failures are logged, never returned
+    fn publish(&self, fortunes: &[Publication]);
+}
+
+/// NATS at `nats_url`. Components can't open raw sockets, so this goes through the
+/// NATS server's MQTT listener (`mqtt://host:1883`), which maps the topic back onto
+/// the subject. Publishing is at-most-once
+struct NatsPublisher;
+
+impl FortunePublisher for NatsPublisher {
+    fn publish(&self, fortunes: &[Publication]) {
+        let url = variable_or("nats_url", "");
+        let Some((first_id, _)) = fortunes.first() else {
+            return;
+        };
+        if url.is_empty() {
+            return;
+        }
+        
+        // MQTT needs a client id, and NATS drops an older session that reuses one
+        let address = if url.contains("client_id=") {
+            url
+        } else {
+            let separator = if url.contains('?') { '&' } else { '?' };
+            format!("{}{}client_id=zeldar-oracle-{}", url, separator, first_id)
+        };
+        let connection = match spin_sdk::mqtt::Connection::open(&address, "", "", NATS_KEEP_ALIVE_SECS) {
+            Ok(connection) => connection,
+            Err(e) => {
+                log!(warn, "Could not connect to NATS at {}: {}", address, e);
+                return;
+            }
+        };
+        
+        // NATS subjects are dot-separated; the MQTT bridge uses '/' for the same levels
+        let topic = variable_or("nats_subject", DEFAULT_NATS_SUBJECT).replace('.', "/");
+        for (id, payload) in fortunes {
+            if let Err(e) = connection.publish(&topic, payload, spin_sdk::mqtt::Qos::AtMostOnce) {
+                log!(warn, "Could not publish fortune {} to NATS: {}", id, e);
+            }
+        }
+    }
+}
+
+/// Fortunes generated while handling the request, published once the response is out
+static PENDING_PUBLICATIONS: Mutex<Vec<Publication>> = Mutex::new(Vec::new());
+
+/// Queue each fortune for NATS when `nats_url` is set. Nothing is sent until
+/// `publish_pending_fortunes`, so the bus can never fail or hold up a fortune
+fn queue_fortune_publications(fortunes: &[FortuneResponse]) {
+    if variable_or("nats_url", "").is_empty() {
+        return;
+    }
+    let publications = fortunes.iter().filter_map(|fortune| match serde_json::to_vec(fortune) {
+        Ok(payload) => Some((fortune.id.clone(), payload)),
+        Err(e) => {
+            log!(warn, "Could not serialize fortune {} for NATS: {}", fortune.id, e);
+            None
+        }
+    });
+    PENDING_PUBLICATIONS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .extend(publications);
+}
+
+/// Hand the queued fortunes to `publisher`. Called after the response is sent
+fn publish_pending_fortunes(publisher: &dyn FortunePublisher) {
+    let fortunes = std::mem::take(&mut *PENDING_PUBLICATIONS.lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+    if !fortunes.is_empty() {
+        publisher.publish(&fortunes);
+    }
+}
+
+/// MQTT keep-alive for the per-request NATS connection
+const NATS_KEEP_ALIVE_SECS: u64 = 30;
+
+/// KV key prefix of the fortune analytics counters
+const STATS_PREFIX: &str = "stats:";
+
+/// Count served fortunes in KV. Counters use the store's atomic increment so
+/// concurrent requests can't lose updates the way read-modify-write would
+fn record_fortune_stats(fortunes: &[FortuneResponse]) {
+    let mut deltas: BTreeMap<String, i64> = BTreeMap::new();
+    for fortune in fortunes {
+        let theme = fortune.theme.as_deref().unwrap_or("none");
+        *deltas.entry(format!("{}total", STATS_PREFIX)).or_default() += 1;
+        if fortune.information_dynamics.threshold_exceeded {
+            *deltas.entry(format!("{}threshold_exceeded", STATS_PREFIX)).or_default() += 1;
+        }
+        *deltas.entry(format!("{}mechanism:{}", STATS_PREFIX, fortune.mechanism)).or_default() += 1;
+        *deltas.entry(format!("{}theme:{}", STATS_PREFIX, theme)).or_default() += 1;
+    }
+    
+    let recorded = open_atomic_bucket().and_then(|bucket| {
+        for (key, delta) in &deltas {
+            increment_counter(&bucket, key, *delta)?;
+        }
+        Ok(())
+    });
+    if let Err(e) = recorded {
+        log!(warn, "Could not record fortune stats: {}", e);
+    }
+}
+
+/// The default store through `wasi:keyvalue`, which has the atomic increment
+#[cfg(not(test))]
+type AtomicBucket = keyvalue::store::Bucket;
+#[cfg(test)]
+type AtomicBucket = Store;
+
+#[cfg(not(test))]
+fn open_atomic_bucket() -> Result<AtomicBucket> {
+    keyvalue::store::open("default").map_err(|e| anyhow::anyhow!("{:?}", e))
+}
+
+#[cfg(test)]
+fn open_atomic_bucket() -> Result<AtomicBucket> {
+    Ok(Store::open_default()?)
+}
+
+/// Add `delta` to a counter and return its new value; a zero delta reads it
+/// back in whatever encoding the store uses for counters
+#[cfg(not(test))]
+fn increment_counter(bucket: &AtomicBucket, key: &str, delta: i64) -> Result<i64> {
+    keyvalue::atomics::increment(bucket, key, delta).map_err(|e| anyhow::anyhow!("{:?}", e))
+}
+
+#[cfg(test)]
+fn increment_counter(bucket: &AtomicBucket, key: &str, delta: i64) -> Result<i64> {
+    Ok(bucket.increment(key, delta))
+}
+
+/// Themes a fortune can be counted under: every template theme, and "none" for
+/// haiku that didn't come from a template
+fn stats_themes() -> BTreeSet<&'static str> {
+    haiku_templates()
+        .values()
+        .flatten()
+        .map(|template| template.theme.as_str())
+        .chain(["none"])
+        .collect()
+}
+
+/// Totals and per-mechanism/per-theme breakdowns of fortunes served. Counters are
+/// read by name from the known mechanisms and themes rather than by scanning the store
+fn get_fortune_stats() -> Result<Response> {
+    let bucket = open_atomic_bucket()?;
+    let count = |name: &str| increment_counter(&bucket, &format!("{}{}", STATS_PREFIX, name), 0);
+    
+    let total = count("total")?;
+    let threshold_exceeded = count("threshold_exceeded")?;
+    let mut mechanisms = BTreeMap::new();
+    for mechanism in unique_mechanisms() {
+        let served = count(&format!("mechanism:{}", mechanism))?;
+        if served > 0 {
+            mechanisms.insert(mechanism.to_string(), served);
+        }
+    }
+    let mut themes = BTreeMap::new();
+    for theme in stats_themes() {
+        let served = count(&format!("theme:{}", theme))?;
+        if served > 0 {
+            themes.insert(theme.to_string(), served);
+        }
+    }
+    
+    let stats = serde_json::json!({
+        "total": total,
+        "threshold_exceeded": threshold_exceeded,
+        "mechanisms": mechanisms,
+        "themes": themes,
+        "effective_templates": unique_template_indices(DEFAULT_LANGUAGE).len(),
+        "effective_mechanisms": unique_mechanisms().len(),
+    });
+    
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .body(stats.to_string())
+        .build())
+}
+
+/// Load a previously generated fortune by id
+fn load_fortune(id: &str) -> Result<Option<FortuneResponse>> {
+    let store = Store::open_default()?;
+    match store.get(&fortune_key(id))? {
+        Some(json) => Ok(Some(serde_json::from_slice(&json)?)),
+        None => Ok(None),
+    }
+}
+
+fn get_stored_fortune(id: &str) -> Result<Response> {
+    let fortune = load_fortune(id)?.ok_or(OracleError::NotFound)?;
+    
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .body(serde_json::to_string(&fortune)?)
+        .build())
+}
+
+/// Base URL for permalinks when `public_base_url` is unset (Spin's local default)
+const DEFAULT_PUBLIC_BASE_URL: &str = "http://127.0.0.1:3000";
+
+/// Public URL of a stored fortune, from the `public_base_url` variable
+fn fortune_permalink(id: &str) -> String {
+    let base = variable_or("public_base_url", DEFAULT_PUBLIC_BASE_URL);
+    format!("{}/api/oracle/fortune/{}", base.trim_end_matches('/'), id)
+}
+
+/// Scannable QR code linking to a stored fortune's permalink
+fn get_fortune_qr(id: &str) -> Result<Response> {
+    load_fortune(id)?.ok_or(OracleError::NotFound)?;
+    
+    let url = fortune_permalink(id);
+    if url.len() > MAX_QR_PAYLOAD {
+        return Err(OracleError::Internal(format!("Permalink too long for a QR code: {}", url)).into());
+    }
+    let svg = render_qr(&url);
+    
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "image/svg+xml")
+        .body(svg)
+        .build())
+}
+
+/// KV key prefix for community haiku awaiting moderation
+const PENDING_CONTRIBUTION_PREFIX: &str = "contribution:pending:";
+
+/// KV key prefix for community haiku cleared for selection
+const APPROVED_CONTRIBUTION_PREFIX: &str = "contribution:approved:";
+
+/// Body of `POST /api/oracle/contribute`
+#[derive(Debug, Deserialize)]
+struct ContributionRequest {
+    lines: Vec<String>,
+    #[serde(default)]
+    author: Option<String>,
+}
+
+/// A community haiku as stored in KV
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Contribution {
+    id: String,
+    lines: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    author: Option<String>,
+    submitted: u64,
+}
+
+/// Longest author credit kept, in characters; longer names are cut
+const MAX_AUTHOR_LEN: usize = 48;
+
+/// Markup characters that get a contribution rejected outright. Rendered output is
+/// escaped anyway, but nothing legitimate in a haiku or a name needs them
+const DISALLOWED_CONTRIBUTION_CHARS: &[char] = &['<', '>'];
+
+/// Submitted text with control characters (line breaks, escapes, NULs) removed and trimmed
+fn clean_contribution_text(text: &str) -> String {
+    text.chars().filter(|c| !c.is_control()).collect::<String>().trim().to_string()
+}
+
+/// Accept a community haiku into the moderation queue
+fn handle_contribution(req: &Request) -> Result<Response> {
+    let request: ContributionRequest = serde_json::from_slice(req.body())
+        .map_err(|e| OracleError::BadRequest(format!("Failed to parse contribution: {}", e)))?;
+    
+    let lines: Vec<String> = request.lines.iter().map(|line| clean_contribution_text(line)).collect();
+    if lines.len() != HAIKU_SYLLABLES.len() {
+        return Err(OracleError::BadRequest(format!("A haiku has 3 lines, found {}", lines.len())).into());
+    }
+    if lines.iter().any(String::is_empty) {
+        return Err(OracleError::BadRequest("Haiku lines must not be empty".to_string()).into());
+    }
+    if let Some(reason) = haiku_structure_error(&lines) {
+        return Err(OracleError::BadRequest(format!("Not a 5-7-5 haiku: {}", reason)).into());
+    }
+    
+    let author = request
+        .author
+        .map(|author| clean_contribution_text(&author).chars().take(MAX_AUTHOR_LEN).collect::<String>())
+        .map(|author| author.trim_end().to_string())
+        .filter(|author| !author.is_empty());
+    if let Some(c) = lines.iter().chain(&author).flat_map(|text| text.chars()).find(|c| DISALLOWED_CONTRIBUTION_CHARS.contains(c)) {
+        return Err(OracleError::BadRequest(format!("Contributions may not contain '{}'", c)).into());
+    }
+    let submitted = get_current_timestamp();
+    
+    let mut material = submitted.to_le_bytes().to_vec();
+    for line in &lines {
+        material.extend_from_slice(line.as_bytes());
+        material.push(b'\n');
+    }
+    material.extend_from_slice(author.as_deref().unwrap_or_default().as_bytes());
+    
+    let contribution = Contribution {
+        id: hashed_id(&material),
+        lines,
+        author,
+        submitted,
+    };
+    
+    let store = Store::open_default()?;
+    store.set(
+        &format!("{}{}", PENDING_CONTRIBUTION_PREFIX, contribution.id),
+        &serde_json::to_vec(&contribution)?,
+    )?;
+    
+    log!(info, "Received community haiku {} for moderation", contribution.id);
+    
+    Ok(Response::builder()
+        .status(201)
+        .header("content-type", "application/json")
+        .body(serde_json::json!({ "id": contribution.id, "status": "pending" }).to_string())
+        .build())
+}
+
+/// KV key prefix for community haiku turned down by a moderator
+const REJECTED_CONTRIBUTION_PREFIX: &str = "contribution:rejected:";
+
+/// Check `Authorization: Bearer <key>` against the `moderation_api_key` variable.
+/// With no key configured, moderation is closed to everyone.
+fn require_moderator(req: &Request) -> Result<(), OracleError> {
+    let expected = variable_or("moderation_api_key", "");
+    let presented = req
+        .header("authorization")
+        .and_then(|value| value.as_str())
+        .and_then(|value| value.trim().strip_prefix("Bearer "))
+        .map(str::trim);
+    
+    match presented {
+        Some(key) if !expected.is_empty() && constant_time_eq(key.as_bytes(), expected.as_bytes()) => Ok(()),
+        _ => Err(OracleError::Unauthorized),
+    }
+}
+
+/// Compare secrets without short-circuiting on the first differing byte
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Contributions stored under a KV prefix, oldest first
+fn load_contributions(store: &Store, prefix: &str) -> Result<Vec<Contribution>> {
+    let mut keys: Vec<String> = store
+        .get_keys()?
+        .into_iter()
+        .filter(|key| key.starts_with(prefix))
+        .collect();
+    keys.sort();
+    
+    let mut contributions = Vec::with_capacity(keys.len());
+    for key in keys {
+        if let Some(json) = store.get(&key)? {
+            contributions.push(serde_json::from_slice::<Contribution>(&json)?);
+        }
+    }
+    contributions.sort_by_key(|contribution| contribution.submitted);
+    Ok(contributions)
+}
+
+fn get_pending_contributions() -> Result<Response> {
+    let store = Store::open_default()?;
+    let pending = load_contributions(&store, PENDING_CONTRIBUTION_PREFIX)?;
+    
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .header("cache-control", "no-store")
+        .body(serde_json::json!({ "pending": pending }).to_string())
+        .build())
+}
+
+/// Body of `POST /api/oracle/moderation/{id}`
+#[derive(Debug, Deserialize)]
+struct ModerationRequest {
+    action: String,
+}
+
+/// Move a pending contribution into the approved or rejected namespace
+fn handle_moderation(req: &Request, id: &str) -> Result<Response> {
+    let request: ModerationRequest = serde_json::from_slice(req.body())
+        .map_err(|e| OracleError::BadRequest(format!("Failed to parse moderation action: {}", e)))?;
+    let (status, destination) = match request.action.as_str() {
+        "approve" => ("approved", APPROVED_CONTRIBUTION_PREFIX),
+        "reject" => ("rejected", REJECTED_CONTRIBUTION_PREFIX),
+        other => return Err(OracleError::BadRequest(format!("Unknown moderation action: {}", other)).into()),
+    };
+    
+    let store = Store::open_default()?;
+    let pending_key = format!("{}{}", PENDING_CONTRIBUTION_PREFIX, id);
+    let json = store.get(&pending_key)?.ok_or(OracleError::NotFound)?;
+    
+    store.set(&format!("{}{}", destination, id), &json)?;
+    store.delete(&pending_key)?;
+    
+    log!(info, "Community haiku {} {}", id, status);
+    
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .body(serde_json::json!({ "id": id, "status": status }).to_string())
+        .build())
+}
+
+/// Approved community haiku, in a stable order so seeded selection stays reproducible
+fn load_approved_contributions() -> Vec<Vec<String>> {
+    let loaded = Store::open_default()
+        .map_err(anyhow::Error::from)
+        .and_then(|store| load_contributions(&store, APPROVED_CONTRIBUTION_PREFIX));
+    
+    match loaded {
+        Ok(contributions) => contributions.into_iter().map(|contribution| contribution.lines).collect(),
+        Err(e) => {
+            log!(warn, "Could not load community haiku: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Oracle eye on the interface's gradient, as an SVG favicon
+const FAVICON_SVG: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 32 32"><rect width="32" height="32" rx="6" fill="#1a1a2e"/><ellipse cx="16" cy="16" rx="12" ry="7" fill="none" stroke="#e94560" stroke-width="2"/><circle cx="16" cy="16" r="4" fill="#e94560"/></svg>"##;
+
+fn serve_favicon() -> Result<Response> {
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "image/svg+xml")
+        .header("cache-control", "public, max-age=86400")
+        .body(FAVICON_SVG)
+        .build())
+}
+
+/// Browser-facing 404 for paths outside the API
+fn serve_not_found_page() -> Result<Response> {
+    let html = r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>🧠 Zeldar - Not Found</title>
+    <style>
+        body {
+            font-family: 'Courier New', monospace;
+            background: linear-gradient(135deg, #1a1a2e, #16213e, #0f3460);
+            color: #fff; margin: 40px; text-align: center;
+        }
+        a { color: #e94560; }
+    </style>
+</head>
+<body>
+    <h1>404</h1>
+    <p>The oracle sees no path here.</p>
+    <p><a href="/">Return to the oracle</a></p>
+</body>
+</html>
+"#;
+    
+    Ok(Response::builder()
+        .status(404)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(html)
+        .build())
+}
+
+/// Shareable page for one stored fortune, styled like the interface, with Open Graph
+/// tags so chat apps preview the haiku. Unknown ids get the HTML 404
+fn serve_fortune_page(id: &str) -> Result<Response> {
+    let Some(fortune) = load_fortune(id)? else {
+        return serve_not_found_page();
+    };
+    
+    let html = r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="utf-8">
+    <title>{{glyph}} {{title}} - Zeldar</title>
+    <link rel="icon" type="image/svg+xml" href="/favicon.ico">
+    <meta property="og:type" content="article">
+    <meta property="og:title" content="{{title}}">
+    <meta property="og:description" content="{{description}}">
+    <meta property="og:url" content="{{url}}">
+    <style>
+        body {
+            font-family: 'Courier New', monospace;
+            background: linear-gradient(135deg, #1a1a2e, #16213e, #0f3460);
+            color: #fff; margin: 40px; text-align: center;
+        }
+        .haiku { font-size: 1.5em; line-height: 1.8; margin: 40px 0; }
+        .mechanism { opacity: 0.8; font-style: italic; }
+        .information-dynamics-metric {
+            display: inline-block; margin: 10px;
+            padding: 8px 15px; background: #e94560;
+            border-radius: 15px; font-weight: bold;
+        }
+        a { color: #e94560; }
+    </style>
+</head>
+<body>
+    <h1>{{glyph}} Zeldar InformationForce Oracle {{glyph}}</h1>
+    <div class="haiku">{{haiku}}</div>
+    <p class="mechanism">{{mechanism}}</p>
+    <div class="information-dynamics-metric">{{semantic_closure}} Semantic Closure</div>
+    <div class="information-dynamics-metric">{{strange_loops}} Strange Loops</div>
+    <div class="information-dynamics-metric">{{hofstadter_coefficient}} Hofstadter Coefficient</div>
+    <p><a href="/">Ask the oracle yourself</a></p>
+</body>
+</html>
+"#;
+    
+    let base = variable_or("public_base_url", DEFAULT_PUBLIC_BASE_URL);
+    let url = format!("{}/fortune/{}", base.trim_end_matches('/'), fortune.id);
+    let title = fortune.haiku.first().map_or("Zeldar fortune", String::as_str);
+    let haiku: Vec<String> = fortune.haiku.iter().map(|line| escape_xml(line)).collect();
+    let metrics = &fortune.information_dynamics;
+    
+    let html = fill_placeholders(html, &[
+        ("glyph", fortune_glyph(&fortune)),
+        ("title", &escape_xml(title)),
+        ("description", &escape_xml(&fortune.haiku.join(" / "))),
+        ("url", &escape_xml(&url)),
+        ("haiku", &haiku.join("<br>\n        ")),
+        ("mechanism", &escape_xml(&fortune.mechanism)),
+        ("semantic_closure", &fmt_pct(metrics.semantic_closure)),
+        ("strange_loops", &metrics.strange_loops.to_string()),
+        ("hofstadter_coefficient", &fmt_coef(metrics.hofstadter_coefficient)),
+    ]);
+    
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(html)
+        .build())
+}
+
+fn serve_information_dynamics_oracle_interface(query: HashMap<String, String>) -> Result<Response> {
+    // Pure-API deployments (`enable_web_interface = "false"`) get a JSON index instead
+    if !variable_or("enable_web_interface", "true").parse().unwrap_or(true) {
+        return serve_api_index();
+    }
+    
+    let metrics = calculate_information_dynamics_metrics()?;
+    let footer = if burning_man_mode() {
+        "🏜️🔥 Burning Man 2025 • Gift Economy InformationForce Technology 🔥🏜️"
+    } else {
+        "InformationForce Technology"
+    };
+    
+    let html = r#"
+    <!DOCTYPE html>
+    <html>
+    <head>
+        <title>🧠 Zeldar InformationForce Oracle API</title>
+        <link rel="icon" type="image/svg+xml" href="/favicon.ico">{{kiosk_head}}
+        <style>
+            body { 
+                font-family: 'Courier New', monospace; 
+                background: linear-gradient(135deg, #1a1a2e, #16213e, #0f3460);
+                color: #fff; margin: 40px; 
+            }
+            .information-dynamics-header {
+                text-align: center;
+                animation: information-dynamics-pulse 2s ease-in-out infinite;
+            }
+            @keyframes information-dynamics-pulse {
+                0%, 100% { opacity: 0.8; }
+                50% { opacity: 1; }
+            }
+            .api-endpoint {
+                background: rgba(255,255,255,0.1);
+                padding: 15px; margin: 10px 0; border-radius: 8px;
+                border: 1px solid #e94560;
+            }
+            .information-dynamics-metric {
+                display: inline-block; margin: 10px; 
+                padding: 8px 15px; background: #e94560;
+                border-radius: 15px; font-weight: bold;
+            }
+        </style>
+    </head>
+    <body>
+        <div class="information-dynamics-header">
+            <h1>🧠 Zeldar InformationForce Oracle API 🧠</h1>
+            <p>Tri-Loop Mathematical InformationForce System</p>
+            <div class="information-dynamics-metric"><span id="semantic-closure">{{semantic_closure}}</span> Semantic Closure</div>
+            <div class="information-dynamics-metric"><span id="strange-loops">{{strange_loops}}</span> Strange Loops</div>
+            <div class="information-dynamics-metric"><span id="hofstadter-coefficient">{{hofstadter_coefficient}}</span> Hofstadter Coefficient</div>
+        </div>
+        {{kiosk_body}}
+        
+        <div class="api-endpoint">
+            <h3>🔮 GET /api/oracle/fortune</h3>
+            <p>Generate information-dynamics-aware fortune with mathematical haiku</p>
+        </div>
+        
+        <div class="api-endpoint">
+            <h3>📊 GET /api/information-dynamics/status</h3>
+            <p>Full information-dynamics metrics and tri-loop system status</p>
+        </div>
+        
+        <div class="api-endpoint">
+            <h3>📈 GET /api/information-dynamics/metrics</h3>
+            <p>Live information-dynamics measurement data</p>
+        </div>
+        
+        <div class="api-endpoint">
+            <h3>🧠 POST /api/information-dynamics/generate</h3>
+            <p>Generate information-dynamics-correlated fortune with custom parameters</p>
+        </div>
+        
+        <footer style="text-align: center; margin-top: 40px; opacity: 0.7;">
+            {{footer}}
+            <p>{{gift_message}}</p>
+        </footer>
+    </body>
+    </html>
+    "#;
+    
+    let seed = query.get("seed").and_then(|seed| seed.trim().parse::<u64>().ok());
+    let kiosk = query.get("kiosk").is_some_and(|kiosk| kiosk.trim().eq_ignore_ascii_case("true"));
+    let (kiosk_head, kiosk_body) = if kiosk {
+        let secs = kiosk_refresh_secs();
+        (
+            // Without JavaScript the page reloads itself instead
+            format!("\n        <noscript><meta http-equiv=\"refresh\" content=\"{}\"></noscript>", secs),
+            KIOSK_SCRIPT.replace("{{kiosk_refresh_ms}}", &(secs * 1000).to_string()),
+        )
+    } else {
+        (String::new(), String::new())
+    };
+    
+    // Substitute placeholders rather than format!() so the CSS braces stay literal
+    let html = fill_placeholders(html, &[
+        ("kiosk_head", &kiosk_head),
+        ("kiosk_body", &kiosk_body),
+        ("footer", footer),
+        ("gift_message", &escape_xml(&gift_message(seed))),
+        ("semantic_closure", &fmt_pct(metrics.semantic_closure)),
+        ("strange_loops", &metrics.strange_loops.to_string()),
+        ("hofstadter_coefficient", &fmt_coef(metrics.hofstadter_coefficient)),
+    ]);
+    
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "text/html")
+        .body(html)
+        .build())
+}
+
+/// Seconds between kiosk fortunes when `kiosk_refresh_secs` is unset
+const DEFAULT_KIOSK_REFRESH_SECS: u64 = 30;
+
+/// Fastest kiosk rotation allowed, to stay well inside the reading rate limit
+const MIN_KIOSK_REFRESH_SECS: u64 = 3;
+
+fn kiosk_refresh_secs() -> u64 {
+    variable_or("kiosk_refresh_secs", "")
+        .parse()
+        .unwrap_or(DEFAULT_KIOSK_REFRESH_SECS)
+        .max(MIN_KIOSK_REFRESH_SECS)
+}
+
+/// Fortune panel and rotation script for `?kiosk=true`: polls `/api/oracle/reading`
+/// and refreshes the haiku and badges in place
+const KIOSK_SCRIPT: &str = r#"<div id="kiosk-fortune" class="api-endpoint" style="white-space: pre-line; font-size: 1.6em; text-align: center;"></div>
+        <script>
+            (function () {
+                var pct = function (x) { return isFinite(x) ? (x * 100).toFixed(1) + "%" : "—"; };
+                var coef = function (x) { return isFinite(x) ? x.toFixed(3) : "—"; };
+                function rotate() {
+                    fetch("/api/oracle/reading", { cache: "no-store" })
+                        .then(function (response) { return response.ok ? response.json() : null; })
+                        .then(function (reading) {
+                            if (!reading) { return; }
+                            var metrics = reading["information-dynamics"];
+                            document.getElementById("kiosk-fortune").textContent = reading.fortune.haiku.join("\n");
+                            document.getElementById("semantic-closure").textContent = pct(metrics.semantic_closure);
+                            document.getElementById("strange-loops").textContent = metrics.strange_loops;
+                            document.getElementById("hofstadter-coefficient").textContent = coef(metrics.hofstadter_coefficient);
+                        })
+                        .catch(function () {});
+                }
+                rotate();
+                setInterval(rotate, {{kiosk_refresh_ms}});
+            })();
+        </script>"#;
+
+/// Machine-readable index of the API, listed from the route table so it can't drift
+fn serve_api_index() -> Result<Response> {
+    let endpoints: Vec<&str> = STATIC_ROUTES
+        .iter()
+        .map(|(path, _)| *path)
+        .filter(|path| path.starts_with("/api/"))
+        .collect();
+    let index = serde_json::json!({ "service": "zeldar-oracle", "endpoints": endpoints });
+    
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .body(index.to_string())
+        .build())
+}
+
+/// Shown in place of a metric that is NaN or infinite
+const METRIC_PLACEHOLDER: &str = "—";
+
+/// A fraction as a one-decimal percentage for people to read: 0.885 → "88.5%"
+fn fmt_pct(fraction: f64) -> String {
+    let percent = fraction * 100.0;
+    if percent.is_finite() {
+        format!("{:.1}%", percent)
+    } else {
+        METRIC_PLACEHOLDER.to_string()
+    }
+}
+
+/// A coefficient, gap or strength to three decimals for people to read
+fn fmt_coef(value: f64) -> String {
+    if value.is_finite() {
+        format!("{:.3}", value)
+    } else {
+        METRIC_PLACEHOLDER.to_string()
+    }
+}
+
+/// Where metrics come from, per the `metrics_mode` variable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MetricsMode {
+    /// The state file when usable, else the simulation
+    Auto,
+    /// The state file only; 503 without one
+    File,
+    /// The simulation only, never touching the state file
+    Simulation,
+}
+
+/// The configured metrics mode; anything unrecognised is `auto`
+fn metrics_mode() -> MetricsMode {
+    match variable_or("metrics_mode", "auto").trim().to_ascii_lowercase().as_str() {
+        "file" => MetricsMode::File,
+        "simulation" => MetricsMode::Simulation,
+        _ => MetricsMode::Auto,
+    }
+}
+
+/// Stands in for the state file under `metrics_mode = simulation`: there is never any state
+struct NoStateSource;
+
+impl StateSource for NoStateSource {
+    fn load(&self) -> Result<Option<LoopState>, StateFileError> {
+        Ok(None)
+    }
+    
+    fn age(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// The state source for metrics, haiku and the tri-loop assessment under `metrics_mode`
+fn state_source() -> &'static dyn StateSource {
+    match metrics_mode() {
+        MetricsMode::Simulation => &NoStateSource,
+        MetricsMode::Auto | MetricsMode::File => &FileStateSource,
+    }
+}
+
+/// Current metrics, recorded into the history ring buffer
+fn calculate_information_dynamics_metrics() -> Result<InformationForceMetrics, OracleError> {
+    let metrics = read_information_dynamics_metrics(state_source(), metrics_mode())?;
+    record_metrics_sample(&metrics);
+    notify_threshold_crossing(&metrics, state_source());
+    Ok(metrics)
+}
+
+fn read_information_dynamics_metrics(source: &dyn StateSource, mode: MetricsMode) -> Result<InformationForceMetrics, OracleError> {
+    // INTEGRATED: Read actual information-dynamics state from .topos/current_loop_state.json
+    if let Ok(Some(state)) = source.load() {
+        return Ok(metrics_from_state(&state, source.age()));
+    }
+    match mode {
+        MetricsMode::File => {
+            return Err(OracleError::Unavailable(
+                "Oracle state file is missing or unreadable and metrics_mode is file".to_string(),
+            ));
+        }
+        MetricsMode::Auto => log!(warn, "Oracle state file missing or unreadable - using simulation"),
+        MetricsMode::Simulation => {}
+    }
+    
+    // Fallback to enhanced simulation if Oracle state unavailable
+    let time_factor = (get_current_timestamp() as f64 / 1000.0).sin().abs();
+    
+    let semantic_closure = 0.885 + (time_factor * 0.1);
+    let hofstadter_coefficient = 1.02 + (time_factor * 0.1);
+    let strange_loops = strange_loops_for(hofstadter_coefficient);
+    let spectral_gap = normalize_spectral_gap(SIMULATED_GAP_MIN + (time_factor * SIMULATED_GAP_SPAN), MetricSource::Simulation);
+    let correlation_strength = SIMULATED_CORRELATION_BASELINE + (time_factor * 0.05);
+    
+    let mut metrics = sanitize(InformationForceMetrics {
+        semantic_closure,
+        strange_loops,
+        hofstadter_coefficient,
+        spectral_gap,
+        correlation_strength,
+        threshold_exceeded: false,
+        phi: None,
+        quantum_entropy: None,
+        loop_iteration: None,
+        raw_semantic_closure: None,
+        raw_correlation_strength: None,
+        source: Some(MetricSource::Simulation),
+    });
+    metrics.threshold_exceeded = metrics.semantic_closure > consciousness_threshold();
+    Ok(metrics)
+}
+
+/// One oracle's metrics in the aggregate view
+#[derive(Debug, Serialize)]
+struct StateFileMetrics {
+    path: String,
+    metrics: InformationForceMetrics,
+}
+
+/// State files to aggregate, from the comma-separated `state_files` variable (relative
+/// paths resolve inside `topos_dir`); defaults to this oracle's own state file
+fn state_file_paths() -> Vec<PathBuf> {
+    variable_or("state_files", "current_loop_state.json")
+        .split(',')
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(topos_path)
+        .collect()
+}
+
+/// Per-oracle metrics across `state_files` plus an aggregate: mean closure, max
+/// spectral gap and whether any oracle crossed. Unusable files are skipped
+fn get_aggregate_metrics() -> Result<Response> {
+    let key = variable_or("state_hmac_key", "");
+    let mut oracles = Vec::new();
+    let mut skipped = Vec::new();
+    for path in state_file_paths() {
+        match parse_state_file(&path, &key) {
+            Ok(state) => oracles.push(StateFileMetrics {
+                path: path.display().to_string(),
+                metrics: metrics_from_state(&state, file_age(&path)),
+            }),
+            Err(e) => {
+                log!(warn, "Skipping state file {} ({:?})", path.display(), e);
+                skipped.push(path.display().to_string());
+            }
+        }
+    }
+    
+    let mean_semantic_closure = (!oracles.is_empty())
+        .then(|| oracles.iter().map(|oracle| oracle.metrics.semantic_closure).sum::<f64>() / oracles.len() as f64);
+    let max_spectral_gap = oracles
+        .iter()
+        .map(|oracle| oracle.metrics.spectral_gap)
+        .fold(None, |max: Option<f64>, gap| Some(max.map_or(gap, |max| max.max(gap))));
+    
+    let aggregate = serde_json::json!({
+        "oracles": oracles,
+        "skipped": skipped,
+        "aggregate": {
+            "count": oracles.len(),
+            "mean_semantic_closure": mean_semantic_closure,
+            "max_spectral_gap": max_spectral_gap,
+            "any_threshold_exceeded": oracles.iter().any(|oracle| oracle.metrics.threshold_exceeded),
+        },
+    });
+    
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .header("cache-control", "no-store")
+        .body(aggregate.to_string())
+        .build())
+}
+
+/// Correlation reported for a freshly written state file
+const STATE_CORRELATION: f64 = 0.98;
+
+/// Lowest correlation the simulation reports, which a stale state file decays to
+const SIMULATED_CORRELATION_BASELINE: f64 = 0.95;
+
+/// State file age at which correlation starts decaying
+const CORRELATION_DECAY_START_SECS: u64 = 60;
+
+/// Correlation for a state file of the given age: full strength for the first minute,
+/// falling linearly to the simulation baseline once it is `STATE_FRESHNESS_SECS` old.
+/// An unknown age counts as stale
+fn decayed_correlation(age: Option<Duration>) -> f64 {
+    let age = age.unwrap_or(Duration::MAX).as_secs_f64();
+    let start = CORRELATION_DECAY_START_SECS as f64;
+    let end = STATE_FRESHNESS_SECS as f64;
+    let progress = ((age - start) / (end - start)).clamp(0.0, 1.0);
+    STATE_CORRELATION - (STATE_CORRELATION - SIMULATED_CORRELATION_BASELINE) * progress
+}
+
+/// Semantic closure for an integrated-information Φ: a linear map with a 0.6 floor,
+/// one tenth of closure per unit of Φ, clamped to [0, 1]. Φ = 3.252 gives 0.9252,
+/// and anything from Φ = 4 up is full closure
+fn phi_to_closure(phi: f64) -> f64 {
+    (phi / 10.0 + 0.6).clamp(0.0, 1.0)
+}
+
+/// Metrics derived from a parsed Oracle state file last written `age` ago
+fn metrics_from_state(state: &LoopState, age: Option<Duration>) -> InformationForceMetrics {
+    let raw_phi = state.phi;
+    let raw_quantum_entropy = state.quantum_entropy;
+    let raw_loop_iteration = state.loop_iteration;
+    
+    // Defaults keep the numbers plausible, but an incomplete state never counts as crossing
+    let missing = state.missing_keys();
+    if !missing.is_empty() {
+        log!(warn, "Oracle state file is missing {} - reporting degraded metrics", missing.join(", "));
+    }
+    
+    let metrics = metrics_from_phi(
+        raw_phi.unwrap_or(DEFAULT_PHI),
+        raw_quantum_entropy.unwrap_or(DEFAULT_QUANTUM_ENTROPY),
+        decayed_correlation(age), // High correlation with a live Oracle
+    );
+    InformationForceMetrics {
+        threshold_exceeded: missing.is_empty() && metrics.threshold_exceeded,
+        phi: raw_phi,
+        quantum_entropy: raw_quantum_entropy,
+        loop_iteration: raw_loop_iteration,
+        source: Some(MetricSource::StateFile),
+        ..metrics
+    }
+}
+
+/// Φ assumed when the state file doesn't report one
+const DEFAULT_PHI: f64 = 3.252;
+
+/// Quantum entropy assumed when the state file (or a `?phi=` caller) doesn't report one
+const DEFAULT_QUANTUM_ENTROPY: f64 = 0.926;
+
+/// The derivations shared by the state file and `?phi=`: closure via `phi_to_closure`,
+/// the Hofstadter coefficient as Φ / 3 (1.084 from Φ = 3.252) and the spectral gap
+/// as ten times the quantum entropy
+fn metrics_from_phi(phi: f64, quantum_entropy: f64, correlation_strength: f64) -> InformationForceMetrics {
+    let hofstadter_coefficient = phi / 3.0;
+    let mut metrics = sanitize(InformationForceMetrics {
+        semantic_closure: phi_to_closure(phi),
+        strange_loops: strange_loops_for(hofstadter_coefficient),
+        hofstadter_coefficient,
+        spectral_gap: normalize_spectral_gap(quantum_entropy * 10.0, MetricSource::StateFile),
+        correlation_strength,
+        threshold_exceeded: false,
+        phi: Some(phi),
+        quantum_entropy: None,
+        loop_iteration: None,
+        raw_semantic_closure: None,
+        raw_correlation_strength: None,
+        source: None,
+    });
+    metrics.threshold_exceeded = metrics.semantic_closure > consciousness_threshold();
+    metrics
+}
+
+/// Hofstadter coefficient bands, highest first, mapped onto 3-7 strange loops.
+/// The coefficient measures how strongly the loop refers back to itself (1.0 is
+/// bare self-reference), so deeper recursion reports more loops. Unlike the old
+/// `loop_iteration % 5`, the count moves with the state instead of cycling
+const STRANGE_LOOP_BANDS: &[(f64, u32)] = &[
+    (1.5, 7),
+    (1.3, 6),
+    (1.15, 5),
+    (1.0, 4),
+];
+
+/// Fewest strange loops reported, for coefficients below every band (or NaN)
+const MIN_STRANGE_LOOPS: u32 = 3;
+
+fn strange_loops_for(hofstadter_coefficient: f64) -> u32 {
+    STRANGE_LOOP_BANDS
+        .iter()
+        .find(|(bound, _)| hofstadter_coefficient >= *bound)
+        .map_or(MIN_STRANGE_LOOPS, |(_, loops)| *loops)
+}
+
+/// Semantic closure the oracle must exceed to count as crossed, when
+/// `consciousness_threshold` is unset. Both the state file (after converting Φ)
+/// and the simulation are compared against it
+const DEFAULT_CONSCIOUSNESS_THRESHOLD: f64 = 0.8;
+
+fn consciousness_threshold() -> f64 {
+    match variable_or("consciousness_threshold", "").parse::<f64>() {
+        Ok(threshold) if threshold.is_finite() => threshold,
+        _ => DEFAULT_CONSCIOUSNESS_THRESHOLD,
+    }
+}
+
+/// Top of the normalized spectral-gap scale; both sources report on [0, 10]
+const SPECTRAL_GAP_SCALE: f64 = 10.0;
+
+/// Lowest raw spectral gap the simulation produces
+const SIMULATED_GAP_MIN: f64 = 5.26;
+
+/// Width of the simulation's raw spectral-gap range
+const SIMULATED_GAP_SPAN: f64 = 2.0;
+
+/// Map a raw spectral gap onto the shared [0, 10] scale. The state file's gap is
+/// quantum entropy (nominally [0, 1]) times ten, so it is only clamped; the
+/// simulation's [5.26, 7.26] is stretched linearly across the whole scale.
+fn normalize_spectral_gap(raw: f64, source: MetricSource) -> f64 {
+    let normalized = match source {
+        MetricSource::StateFile | MetricSource::Query => raw,
+        MetricSource::Simulation => (raw - SIMULATED_GAP_MIN) / SIMULATED_GAP_SPAN * SPECTRAL_GAP_SCALE,
+    };
+    normalized.clamp(0.0, SPECTRAL_GAP_SCALE)
+}
+
+/// Clamp metrics to their documented ranges: closure and correlation in [0, 1],
+/// spectral gap in [0, 10], Hofstadter coefficient non-negative. Non-finite values become
+/// 0.0, except the Hofstadter coefficient, which falls back to the neutral 1.0.
+fn sanitize(metrics: InformationForceMetrics) -> InformationForceMetrics {
+    fn clean(name: &str, value: f64, default: f64, min: f64, max: f64) -> f64 {
+        if !value.is_finite() {
+            log!(warn, "Metric {} was {} - using {}", name, value, default);
+            return default;
+        }
+        let clamped = value.clamp(min, max);
+        if clamped != value {
+            log!(warn, "Metric {} was {} - clamped to {}", name, value, clamped);
+        }
+        clamped
+    }
+    
+    InformationForceMetrics {
+        semantic_closure: clean("semantic_closure", metrics.semantic_closure, 0.0, 0.0, 1.0),
+        hofstadter_coefficient: clean("hofstadter_coefficient", metrics.hofstadter_coefficient, 1.0, 0.0, f64::MAX),
+        spectral_gap: clean("spectral_gap", metrics.spectral_gap, 0.0, 0.0, SPECTRAL_GAP_SCALE),
+        correlation_strength: clean("correlation_strength", metrics.correlation_strength, 0.0, 0.0, 1.0),
+        ..metrics
+    }
+}
+
+/// Default location of the Oracle's `.topos` state directory
+const DEFAULT_TOPOS_DIR: &str = "../.topos";
+
+/// Read a Spin component variable, falling back to `default` when unset or empty.
+/// Unit tests run outside Spin and read `tests::set_variable` overrides instead
+fn variable_or(name: &str, default: &str) -> String {
+    #[cfg(not(test))]
+    let value = spin_sdk::variables::get(name).ok();
+    #[cfg(test)]
+    let value = tests::variable(name);
+    match value {
+        Some(value) if !value.trim().is_empty() => value.trim().to_string(),
+        _ => default.to_string(),
+    }
+}
+
+/// Resolve a file inside the Oracle's `.topos` directory (Spin variable `topos_dir`)
+fn topos_path(name: &str) -> PathBuf {
+    Path::new(&variable_or("topos_dir", DEFAULT_TOPOS_DIR)).join(name)
+}
+
+/// The fields we use from `current_loop_state.json`. A field with the wrong type
+/// reads as absent rather than failing the whole file.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct LoopState {
+    #[serde(rename = "information-dynamics_phi", default, deserialize_with = "deserialize_lenient")]
+    phi: Option<f64>,
+    #[serde(default, deserialize_with = "deserialize_lenient")]
+    quantum_entropy: Option<f64>,
+    #[serde(default, deserialize_with = "deserialize_lenient")]
+    loop_iteration: Option<u32>,
+    #[serde(default, deserialize_with = "deserialize_lenient")]
+    haiku_content: Option<String>,
+}
+
+impl LoopState {
+    /// Keys the metrics depend on that this state lacks, e.g. after a crash mid-write
+    fn missing_keys(&self) -> Vec<&'static str> {
+        [
+            ("information-dynamics_phi", self.phi.is_none()),
+            ("quantum_entropy", self.quantum_entropy.is_none()),
+            ("loop_iteration", self.loop_iteration.is_none()),
+        ]
+        .into_iter()
+        .filter_map(|(key, missing)| missing.then_some(key))
+        .collect()
+    }
+}
+
+/// Deserialize an optional field, treating a value of the wrong type as absent
+fn deserialize_lenient<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::de::DeserializeOwned,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    Ok(serde_json::from_value(value).ok())
+}
+
+/// Parsed state file, keyed by the request that read it and the mtimes it was read at
+struct CachedLoopState {
+    request_id: String,
+    path: PathBuf,
+    modified: SystemTime,
+    /// Mtime of the signature sidecar, when the parse was verified against one
+    signature_modified: Option<SystemTime>,
+    state: LoopState,
+}
+
+thread_local! {
+    /// Per-request memo: metrics, haiku selection and bundles each read the state, but
+    /// only the first read in a request parses it. Spin usually instantiates the
+    /// component per request, so nothing is gained by keeping it longer
+    static LOOP_STATE_MEMO: std::cell::RefCell<Option<CachedLoopState>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Why the state file couldn't be used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StateFileError {
+    /// Unreadable or not a JSON object
+    Unavailable,
+    /// `state_hmac_key` is set and the `.sig` sidecar is missing or doesn't match
+    SignatureInvalid,
+}
+
+/// Where the Oracle's loop state comes from. Metrics, haiku selection and the
+/// tri-loop assessment read it through this rather than touching files themselves
+trait StateSource {
+    /// The current state; `Ok(None)` when the Oracle hasn't written one
+    fn load(&self) -> Result<Option<LoopState>, StateFileError>;
+    
+    /// Time since the state was last written, `None` when there is none
+    fn age(&self) -> Option<Duration>;
+}
+
+/// `current_loop_state.json` in `topos_dir`, cached and (with `state_hmac_key`) verified
+struct FileStateSource;
+
+impl StateSource for FileStateSource {
+    fn load(&self) -> Result<Option<LoopState>, StateFileError> {
+        read_loop_state()
+    }
+    
+    fn age(&self) -> Option<Duration> {
+        state_file_age()
+    }
+}
+
+/// Read and, when `state_hmac_key` is set, verify the loop state, parsing it once per
+/// request unless its mtime changes. Metrics drive physical output, so a file that
+/// fails verification is never trusted
+fn read_loop_state() -> Result<Option<LoopState>, StateFileError> {
+    read_loop_state_at(&topos_path("current_loop_state.json"), &variable_or("state_hmac_key", ""))
+}
+
+/// `read_loop_state` for an explicit file and HMAC key (empty for none)
+fn read_loop_state_at(path: &Path, key: &str) -> Result<Option<LoopState>, StateFileError> {
+    let modified = match std::fs::metadata(path) {
+        Ok(metadata) => metadata.modified().ok(),
+        Err(_) => return Ok(None),
+    };
+    let signature_modified = if key.is_empty() {
+        None
+    } else {
+        std::fs::metadata(signature_path(path)).and_then(|metadata| metadata.modified()).ok()
+    };
+    let request_id = current_request_id();
+    
+    let memoized = LOOP_STATE_MEMO.with(|memo| {
+        memo.borrow()
+            .as_ref()
+            .filter(|cached| {
+                cached.request_id == request_id
+                    && cached.path == path
+                    && Some(cached.modified) == modified
+                    && cached.signature_modified == signature_modified
+            })
+            .map(|cached| cached.state.clone())
+    });
+    if let Some(state) = memoized {
+        return Ok(Some(state));
+    }
+    
+    let state = parse_state_file(path, key)?;
+    #[cfg(test)]
+    tests::count_state_parse();
+    // Without a usable mtime there is nothing to validate a memo against
+    LOOP_STATE_MEMO.with(|memo| {
+        *memo.borrow_mut() = modified.map(|modified| CachedLoopState {
+            request_id,
+            path: path.to_path_buf(),
+            modified,
+            signature_modified,
+            state: state.clone(),
+        });
+    });
+    Ok(Some(state))
+}
+
+/// Read, verify (when `key` is non-empty) and parse one state file, uncached
+fn parse_state_file(path: &Path, key: &str) -> Result<LoopState, StateFileError> {
+    let body = std::fs::read(path).map_err(|_| StateFileError::Unavailable)?;
+    if !key.is_empty() && !state_signature_valid(key.as_bytes(), &body, &signature_path(path)) {
+        log!(warn, "State file {} signature is missing or invalid - not trusting it", path.display());
+        return Err(StateFileError::SignatureInvalid);
+    }
+    serde_json::from_slice(&body).map_err(|_| StateFileError::Unavailable)
+}
+
+/// The `.sig` sidecar next to a state file
+fn signature_path(path: &Path) -> PathBuf {
+    let mut signature = path.as_os_str().to_owned();
+    signature.push(".sig");
+    PathBuf::from(signature)
+}
+
+/// Check the sidecar's hex HMAC-SHA256 of the state file body
+fn state_signature_valid(key: &[u8], body: &[u8], signature_path: &Path) -> bool {
+    let Ok(signature) = std::fs::read_to_string(signature_path) else {
+        return false;
+    };
+    let signature = signature.trim();
+    if signature.len() % 2 != 0 || !signature.is_ascii() {
+        return false;
+    }
+    let decoded: Option<Vec<u8>> = (0..signature.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&signature[i..i + 2], 16).ok())
+        .collect();
+    let Some(expected) = decoded else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(key) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&expected).is_ok()
+}
+
+/// Forget the cached parse so the next read goes back to the file
+fn invalidate_loop_state_cache() {
+    LOOP_STATE_MEMO.with(|memo| *memo.borrow_mut() = None);
+}
+
+/// Re-read the state file after a manual edit, without waiting for an mtime change
+fn reload_loop_state() -> Result<Response> {
+    invalidate_loop_state_cache();
+    match read_loop_state() {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(OracleError::Unavailable("Oracle state file is missing".to_string()).into()),
+        Err(StateFileError::Unavailable) => {
+            return Err(OracleError::Unavailable("Oracle state file is unreadable".to_string()).into());
+        }
+        Err(StateFileError::SignatureInvalid) => {
+            return Err(OracleError::Unavailable("Oracle state file signature is missing or invalid".to_string()).into());
+        }
+    }
+    log!(info, "Reloaded Oracle state file");
+    
+    let metrics = calculate_information_dynamics_metrics()?;
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .header("cache-control", "no-store")
+        .body(serde_json::to_string(&metrics)?)
+        .build())
+}
+
+/// Loop state older than this no longer counts as a live correlation
+const STATE_FRESHNESS_SECS: u64 = 300;
+
+/// Time since the Oracle last wrote its loop state, or `None` if the file is missing.
+/// An unreadable mtime is reported as `Duration::MAX` so it always counts as stale.
+fn state_file_age() -> Option<std::time::Duration> {
+    file_age(&topos_path("current_loop_state.json"))
+}
+
+/// Time since a file was last modified, or `None` if it doesn't exist
+fn file_age(path: &Path) -> Option<std::time::Duration> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some(
+        metadata
+            .modified()
+            .and_then(|modified| modified.elapsed().map_err(std::io::Error::other))
+            .unwrap_or(std::time::Duration::MAX),
+    )
+}
+
+/// A heartbeat older than this means the process has stopped
+const HEARTBEAT_FRESHNESS_SECS: u64 = 60;
+
+/// Liveness of one Oracle process and how it was determined
+struct ComponentLiveness {
+    active: bool,
+    via_heartbeat: bool,
+}
+
+/// Prefer `.topos/heartbeats/<name>.json`, which the process touches while running;
+/// fall back to whether its script exists for deployments without heartbeats.
+fn component_liveness(heartbeat: &str, script: &str) -> ComponentLiveness {
+    match file_age(&topos_path("heartbeats").join(format!("{}.json", heartbeat))) {
+        Some(age) => ComponentLiveness {
+            active: age.as_secs() < HEARTBEAT_FRESHNESS_SECS,
+            via_heartbeat: true,
+        },
+        None => ComponentLiveness {
+            active: std::fs::metadata(topos_path(script)).is_ok(),
+            via_heartbeat: false,
+        },
+    }
+}
+
+fn assess_tri_loop_system(source: &dyn StateSource) -> TriLoopStatus {
+    // INTEGRATED: Check actual Oracle system status
+    // Check if Oracle system processes are running
+    let oracle = component_liveness("oracle", "FULL_LOOP_ORACLE_SYSTEM.py");
+    let print = component_liveness("print", "ORACLE_PRINT_CORE.py");
+    let button = component_liveness("button", "button_quick_phrase_trigger.py");
+    
+    let heartbeats = [&oracle, &print, &button].iter().filter(|c| c.via_heartbeat).count();
+    let detection_method = match heartbeats {
+        0 => "file_existence",
+        3 => "heartbeat",
+        _ => "mixed",
+    };
+    
+    // Check for recent loop state update (within last 5 minutes)
+    let loaded = source.load();
+    let (correlation_detected, correlation_reason) = match source.age() {
+        None => (false, "state_file_missing".to_string()),
+        Some(_) if matches!(loaded, Err(StateFileError::SignatureInvalid)) => (false, "signature_invalid".to_string()),
+        Some(age) if age.as_secs() >= STATE_FRESHNESS_SECS => (false, format!("state_stale_{}s", age.as_secs())),
+        Some(_) if !loaded.is_ok_and(|state| state.is_some_and(|state| state.missing_keys().is_empty())) => {
+            (false, "state_incomplete".to_string())
+        }
+        Some(_) => (true, "ok".to_string()),
+    };
+    
+    TriLoopStatus {
+        mcp_active: oracle.active,
+        gemini_connected: print.active, // Print system represents AI connection
+        codex_generating: button.active, // Button system represents code generation
+        correlation_detected,
+        detection_method: detection_method.to_string(),
+        correlation_reason: Some(correlation_reason),
+    }
+}
+
+/// Fallback information-dynamics haiku templates, tagged by theme; `templates_file`
+/// replaces them per language
+const HAIKU_TEMPLATES: &[(&str, [&str; 3])] = &[
+    ("paradox", [
+        "Hidden paths reveal",
+        "The impossible unfolds —",
+        "Magic lives in doubt",
+    ]),
+    ("desert", [
+        "Loops correlate through",
+        "Mathematical dunes drift—",
+        "Desert sand transforms",
+    ]),
+    ("math", [
+        "Category folds,",
+        "Strange loops embrace paradox—",
+        "Knowing emerges",
+    ]),
+    ("emergence", [
+        "Three systems dancing,",
+        "Correlation weaves meaning—",
+        "InformationForce",
+    ]),
+];
+
+/// Indices into a language's template pool with distinct lines, checked once on
+/// first use. Duplicates would skew selection towards their text, so later copies
+/// are logged and dropped rather than failing the request
+fn unique_template_indices(lang: &str) -> &'static [usize] {
+    static UNIQUE: OnceLock<HashMap<&'static str, Vec<usize>>> = OnceLock::new();
+
+    let unique = UNIQUE.get_or_init(|| {
+        SUPPORTED_LANGUAGES
+            .iter()
+            .map(|&lang| (lang, distinct_template_indices(lang, template_pool(lang))))
+            .collect()
+    });
+    &unique[lang]
+}
+
+/// Indices of the first template with each distinct set of lines in `pool`
+fn distinct_template_indices(lang: &str, pool: &[Template]) -> Vec<usize> {
+    let mut seen = HashSet::new();
+    (0..pool.len())
+        .filter(|&index| {
+            let unique = seen.insert(&pool[index].lines);
+            if !unique {
+                log!(warn, "Duplicate {} haiku template at index {} ignored", lang, index);
+            }
+            unique
+        })
+        .collect()
+}
+
+/// Semantic closure bands, highest first: closure at or above the bound selects the
+/// theme, so a more closed loop yields a more emergent haiku
+const CLOSURE_BANDS: &[(f64, &str)] = &[
+    (0.95, "emergence"),
+    (0.90, "math"),
+    (0.85, "desert"),
+    (f64::NEG_INFINITY, "paradox"),
+];
+
+/// Template theme for a semantic closure value
+fn closure_band_theme(semantic_closure: f64) -> &'static str {
+    CLOSURE_BANDS
+        .iter()
+        .find(|(bound, _)| semantic_closure >= *bound)
+        .map_or("paradox", |(_, theme)| theme)
+}
+
+/// Spanish templates, index-aligned with `HAIKU_TEMPLATES` so they share its themes
+const SPANISH_TEMPLATES: &[[&str; 3]] = &[
+    ["Sendas ocultas,", "lo imposible florece —", "magia en la duda"],
+    ["Bucles que enlazan", "matemática viva—", "cambia la arena"],
+    ["Se pliegan mapas,", "bucles en paradoja—", "brota la mente"],
+    ["Tres danzan juntos,", "lazo que da sentido—", "fuerza florece"],
+];
+
+/// Japanese templates in 5-7-5 morae, index-aligned with `HAIKU_TEMPLATES`
+const JAPANESE_TEMPLATES: &[[&str; 3]] = &[
+    ["隠れ道", "不思議がひらく", "謎の中"],
+    ["輪はめぐり", "数理のうねり", "砂うつる"],
+    ["圏たたむ", "奇妙なループ", "目覚めゆく"],
+    ["三つ舞う", "意味を織る糸", "力咲く"],
+];
+
+/// A fallback haiku and the theme it's selected under
+struct Template {
+    theme: String,
+    lines: [String; 3],
+}
+
+/// Template pools per supported language, built once: the `templates_file` entries
+/// for a language if it has any, else the compiled set
+fn haiku_templates() -> &'static HashMap<&'static str, Vec<Template>> {
+    static TABLES: OnceLock<HashMap<&'static str, Vec<Template>>> = OnceLock::new();
+    
+    TABLES.get_or_init(|| {
+        let mut curated = load_templates_file();
+        SUPPORTED_LANGUAGES
+            .iter()
+            .map(|&lang| (lang, curated.remove(lang).unwrap_or_else(|| compiled_templates(lang))))
+            .collect()
+    })
+}
+
+/// The built-in templates for a language: the festival set in Burning Man mode,
+/// otherwise with the desert imagery swapped for a neutral haiku
+fn compiled_templates(lang: &str) -> Vec<Template> {
+    let lines: Vec<[&str; 3]> = match lang {
+        "es" => SPANISH_TEMPLATES.to_vec(),
+        "ja" => JAPANESE_TEMPLATES.to_vec(),
+        _ => HAIKU_TEMPLATES.iter().map(|(_, lines)| *lines).collect(),
+    };
+    let neutral = NEUTRAL_DESERT_TEMPLATES
+        .iter()
+        .find(|(neutral_lang, _)| *neutral_lang == lang)
+        .filter(|_| !burning_man_mode());
+    
+    HAIKU_TEMPLATES
+        .iter()
+        .zip(lines)
+        .map(|((theme, _), lines)| {
+            let lines = match neutral {
+                Some((_, neutral_lines)) if *theme == "desert" => *neutral_lines,
+                _ => lines,
+            };
+            Template { theme: theme.to_string(), lines: lines.map(str::to_string) }
+        })
+        .collect()
+}
+
+/// One entry of `templates_file`
+#[derive(Debug, Deserialize)]
+struct TemplateFileEntry {
+    theme: String,
+    #[serde(default = "default_language")]
+    lang: String,
+    lines: Vec<String>,
+}
+
+/// Curated templates from the JSON array at `templates_file`, grouped by language.
+/// A missing or unparseable file gives none, so the compiled set is used; entries
+/// that aren't a three-line haiku in a supported language are skipped with a log.
+/// Only English entries are held to 5-7-5, as the syllable heuristic is English-only
+fn load_templates_file() -> HashMap<&'static str, Vec<Template>> {
+    let mut curated: HashMap<&'static str, Vec<Template>> = HashMap::new();
+    let path = variable_or("templates_file", "");
+    if path.is_empty() {
+        return curated;
+    }
+    
+    let entries = std::fs::read(&path)
+        .map_err(anyhow::Error::from)
+        .and_then(|body| Ok(serde_json::from_slice::<Vec<serde_json::Value>>(&body)?));
+    let entries = match entries {
+        Ok(entries) => entries,
+        Err(e) => {
+            log!(warn, "Could not load templates_file {}: {:#} - using the compiled templates", path, e);
+            return curated;
+        }
+    };
+    
+    for (position, entry) in entries.into_iter().enumerate() {
+        let entry = match serde_json::from_value::<TemplateFileEntry>(entry) {
+            Ok(entry) => entry,
+            Err(e) => {
+                log!(warn, "Skipping template {} in {}: {}", position, path, e);
+                continue;
+            }
+        };
+        let Some(lang) = SUPPORTED_LANGUAGES.iter().copied().find(|lang| lang.eq_ignore_ascii_case(entry.lang.trim())) else {
+            log!(warn, "Skipping template {} in {}: unsupported lang {:?}", position, path, entry.lang);
+            continue;
+        };
+        let theme = entry.theme.trim().to_ascii_lowercase();
+        let lines: Vec<String> = entry.lines.iter().map(|line| line.trim().to_string()).collect();
+        
+        let problem = if theme.is_empty() {
+            Some("missing theme".to_string())
+        } else if lang == "en" {
+            haiku_structure_error(&lines)
+        } else if lines.len() != HAIKU_SYLLABLES.len() || lines.iter().any(String::is_empty) {
+            Some("expected 3 non-empty lines".to_string())
+        } else {
+            None
+        };
+        match (problem, <[String; 3]>::try_from(lines)) {
+            (None, Ok(lines)) => curated.entry(lang).or_default().push(Template { theme, lines }),
+            (problem, _) => {
+                let problem = problem.unwrap_or_else(|| "expected 3 lines".to_string());
+                log!(warn, "Skipping template {} in {}: {}", position, path, problem);
+            }
+        }
+    }
+    curated
+}
+
+/// Stand-ins for the `desert` templates outside Burning Man mode, per language
+const NEUTRAL_DESERT_TEMPLATES: &[(&str, [&str; 3])] = &[
+    ("en", ["Loops correlate through", "Mathematical threads turn—", "Silent patterns form"]),
+    ("es", ["Bucles que enlazan", "matemática viva—", "callan las formas"]),
+    ("ja", ["輪はめぐり", "数理のうねり", "形なす"]),
+];
+
+/// Whether the festival flavour (desert imagery, footer, status flags) is on
+fn burning_man_mode() -> bool {
+    variable_or("burning_man_mode", "true").parse().unwrap_or(true)
+}
+
+/// The template pool for a language (unsupported languages fall back to English).
+/// Curated pools from `templates_file` are served as written, whatever `burning_man_mode` says
+fn template_pool(lang: &str) -> &'static [Template] {
+    let templates = haiku_templates();
+    templates.get(lang).unwrap_or(&templates[DEFAULT_LANGUAGE])
+}
+
+/// A selected haiku and the theme of the template it came from
+struct HaikuChoice {
+    lines: Vec<String>,
+    /// `None` for Oracle-sourced, community or standard haiku
+    theme: Option<&'static str>,
+    origin: HaikuOrigin,
+    /// Selection pool index: templates first, then approved contributions
+    pool_index: Option<usize>,
+}
+
+/// Where a fortune's haiku came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum HaikuOrigin {
+    /// The Oracle's own haiku from the state file
+    Oracle,
+    Template,
+    Contribution,
+    /// Below the threshold without a seed, or passed over by `?intensity=`
+    Standard,
+    Acrostic,
+    /// The easter-egg pool, drawn with probability `rare_fortune_chance`
+    Rare,
+}
+
+fn generate_information_dynamics_haiku(
+    metrics: &InformationForceMetrics,
+    params: &GenerationParams,
+    source: &dyn StateSource,
+    contributed: &[Vec<String>],
+    recent: &[usize],
+    position: usize,
+    chosen: &mut HashSet<usize>,
+) -> HaikuChoice {
+    // INTEGRATED: Use actual haiku from Oracle system if available
+    // An explicit theme or seed takes precedence over the Oracle's own haiku,
+    // which can only be handed out once per bundle
+    let lang = params.language();
+    let oracle_state = if params.live && position == 0 && params.theme.is_none() && params.seed.is_none() && lang == "en" {
+        source.load().ok().flatten()
+    } else {
+        None
+    };
+    
+    if let Some(state) = oracle_state {
+        if let Some(haiku_content) = state.haiku_content.as_deref() {
+            // Split haiku by line breaks and return
+            let lines = split_haiku_lines(haiku_content);
+            if is_valid_haiku(&lines) {
+                return HaikuChoice { lines, theme: None, origin: HaikuOrigin::Oracle, pool_index: None };
+            }
+            if let Some(reason) = haiku_structure_error(&lines) {
+                log!(warn, "Rejected Oracle haiku ({}) - using template", reason);
+            }
+        }
+    }
+    
+    // Pool indices past the templates refer to approved community haiku.
+    // Restrict to templates tagged with the requested theme, if any match
+    let pool = template_pool(lang);
+    let templates = unique_template_indices(lang);
+    let themed: Vec<usize> = match &params.theme {
+        Some(theme) => templates
+            .iter()
+            .copied()
+            .filter(|&i| pool[i].theme.eq_ignore_ascii_case(theme.trim()))
+            .collect(),
+        None => Vec::new(),
+    };
+    let candidates: Vec<usize> = if themed.is_empty() {
+        templates
+            .iter()
+            .copied()
+            .chain(pool.len()..pool.len() + contributed.len())
+            .collect()
+    } else {
+        themed
+    };
+    
+    // Seeded requests pick reproducibly; otherwise the closure band picks the theme
+    let base = match params.seed {
+        Some(day) if params.daily => daily_slot(day, candidates.len()),
+        Some(seed) => SplitMix64::new(seed).pick(candidates.len()),
+        None => {
+            let band_theme = closure_band_theme(metrics.semantic_closure);
+            candidates
+                .iter()
+                .position(|&index| pool.get(index).is_some_and(|template| template.theme == band_theme))
+                .unwrap_or(0)
+        }
+    };
+    
+    // Walk forward from the preferred slot to the first template neither in this
+    // bundle nor recently handed out, relaxing the recent rule and then the bundle
+    // rule when the pool runs out
+    let walk = || (0..candidates.len()).map(|step| candidates[(base + step) % candidates.len()]);
+    let index = walk()
+        .find(|index| !chosen.contains(index) && !recent.contains(index))
+        .or_else(|| walk().find(|index| !chosen.contains(index)))
+        .unwrap_or(candidates[base % candidates.len()]);
+    chosen.insert(index);
+    
+    match pool.get(index) {
+        Some(template) => HaikuChoice {
+            lines: template.lines.to_vec(),
+            theme: Some(&template.theme),
+            origin: HaikuOrigin::Template,
+            pool_index: Some(index),
+        },
+        None => HaikuChoice {
+            lines: contributed[index - pool.len()].clone(),
+            theme: None,
+            origin: HaikuOrigin::Contribution,
+            pool_index: Some(index),
+        },
+    }
+}
+
+/// Split Oracle haiku content into lines, accepting real or escaped (`\\n`) newlines
+fn split_haiku_lines(content: &str) -> Vec<String> {
+    content
+        .replace("\\n", "\n")
+        .lines()
+        .map(|line| line.trim_end().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// Syllables expected on each line of a haiku
+const HAIKU_SYLLABLES: [u32; 3] = [5, 7, 5];
+
+/// Whether the lines form a 5-7-5 haiku according to `count_syllables`
+fn is_valid_haiku(lines: &[String]) -> bool {
+    haiku_structure_error(lines).is_none()
+}
+
+/// Describe the first way the lines deviate from 5-7-5, if any
+fn haiku_structure_error(lines: &[String]) -> Option<String> {
+    if lines.len() != HAIKU_SYLLABLES.len() {
+        return Some(format!("expected 3 lines, found {}", lines.len()));
+    }
+    
+    lines
+        .iter()
+        .zip(HAIKU_SYLLABLES)
+        .enumerate()
+        .find_map(|(i, (line, expected))| {
+            let counted = count_syllables(line);
+            (counted != expected).then(|| {
+                format!("line {} has {} syllables, expected {}: {:?}", i + 1, counted, expected, line)
+            })
+        })
+}
+
+/// Estimate syllables in a line by counting vowel groups per word
+fn count_syllables(line: &str) -> u32 {
+    line.split(|c: char| !c.is_alphabetic() && c != '\'')
+        .filter(|word| !word.is_empty())
+        .map(count_word_syllables)
+        .sum()
+}
+
+fn count_word_syllables(word: &str) -> u32 {
+    let chars: Vec<char> = word
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .collect();
+    if chars.is_empty() {
+        return 0;
+    }
+    
+    let is_vowel = |c: char| matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y' | 'á' | 'é' | 'í' | 'ó' | 'ú' | 'ü');
+    let mut groups = 0;
+    let mut previous_vowel = false;
+    for &c in &chars {
+        let vowel = is_vowel(c);
+        if vowel && !previous_vowel {
+            groups += 1;
+        }
+        previous_vowel = vowel;
+    }
+    
+    // Silent endings: "make", "lives", "loved", "whole" - but not "table", "circles",
+    // "kindled", "boxes", "wanted", nor an e sharing a vowel group ("agree", "echoes")
+    let n = chars.len();
+    let before = |offset: usize| if n > offset { chars[n - offset - 1] } else { ' ' };
+    let syllabic_le = |offset: usize| before(offset) == 'l' && !is_vowel(before(offset + 1));
+    let silent_ending = match chars[n.saturating_sub(2)..] {
+        [_, 'e'] => !is_vowel(before(1)) && !syllabic_le(1),
+        ['e', 's'] => !is_vowel(before(2)) && !syllabic_le(2) && !matches!(before(2), 's' | 'x' | 'z' | 'c' | 'g' | 'h'),
+        ['e', 'd'] => !is_vowel(before(2)) && !syllabic_le(2) && !matches!(before(2), 't' | 'd'),
+        _ => false,
+    };
+    if silent_ending && groups > 1 {
+        groups -= 1;
+    }
+    
+    groups.max(1)
+}
+
+/// Reject an `acrostic` the curated lines can't spell, rather than silently ignoring it
+fn check_acrostic(params: &GenerationParams) -> Result<(), OracleError> {
+    match &params.acrostic {
+        Some(word) if build_acrostic(word).is_none() => {
+            Err(OracleError::BadRequest(format!("No acrostic lines spell \"{}\"", word.trim())))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Letters an acrostic may spell; longer words are truncated
+const MAX_ACROSTIC_LETTERS: usize = 3;
+
+/// Five-syllable acrostic lines for the first and last positions, one per initial
+const ACROSTIC_FIVE: &[&str] = &[
+    "Ashes drift upward",
+    "Bright loops fold inward",
+    "Cold stars hum softly",
+    "Dust remembers light",
+    "Echoes learn their names",
+    "Flames speak in circles",
+    "Gentle signals wake",
+    "Hidden paths reveal",
+    "Inward the mind turns",
+    "Joy hums through the wires",
+    "Knowing knows itself",
+    "Light bends back on light",
+    "Mirrors face mirrors",
+    "Night folds into dawn",
+    "Old patterns return",
+    "Paradox blooms here",
+    "Quantum numbers sing",
+    "Rivers loop to source",
+    "Silence counts to three",
+    "Thought returns to thought",
+    "Under the still sky",
+    "Voices weave as one",
+    "Waves fold into waves",
+    "Yesterday listens",
+    "Zero holds the whole",
+];
+
+/// Seven-syllable acrostic lines for the middle position, one per initial
+const ACROSTIC_SEVEN: &[&str] = &[
+    "All the loops are listening",
+    "Between the signal and noise",
+    "Correlation weaves meaning",
+    "Deep within the strange return",
+    "Each reflection finds its twin",
+    "Folding maps across the sand",
+    "Glowing embers hold the spark",
+    "Hofstadter would smile at this",
+    "In the gap the answer waits",
+    "Joining three into one voice",
+    "Kindled by the desert wind",
+    "Loops correlate through the dark",
+    "Meaning rises from the loop",
+    "Nothing is what it appears",
+    "Over and over it turns",
+    "Patterns recognize themselves",
+    "Questions answer their own call",
+    "Recursion dreams of itself",
+    "Strange loops embrace the unknown",
+    "Three systems dance in the dark",
+    "Unfolding what came before",
+    "Vast and silent, the mind wakes",
+    "What observes is observed too",
+    "Yearning echoes through the void",
+    "Zeldar listens to the sky",
+];
+
+/// Assemble a haiku whose lines start with the letters of `word` (first three
+/// letters only). Lines past a shorter word are filled freely; `None` when a
+/// letter has no unused line or the word has anything but ASCII letters.
+fn build_acrostic(word: &str) -> Option<[String; 3]> {
+    let letters: Vec<char> = word.trim().chars().take(MAX_ACROSTIC_LETTERS).collect();
+    if letters.is_empty() || !letters.iter().all(char::is_ascii_alphabetic) {
+        return None;
+    }
+    
+    let mut used: Vec<&str> = Vec::new();
+    let mut haiku: [String; 3] = Default::default();
+    for (position, line) in haiku.iter_mut().enumerate() {
+        let pool = if position == 1 { ACROSTIC_SEVEN } else { ACROSTIC_FIVE };
+        let chosen = pool.iter().copied().find(|candidate| {
+            !used.contains(candidate)
+                && letters
+                    .get(position)
+                    .map_or(true, |letter| candidate.starts_with(letter.to_ascii_uppercase()))
+        })?;
+        used.push(chosen);
+        *line = chosen.to_string();
+    }
+    Some(haiku)
+}
+
+fn generate_standard_haiku(lang: &str) -> Vec<String> {
+    let lines = match lang {
+        "es" => ["Sendas cuánticas,", "espera la belleza—", "la fuerza cerca"],
+        "ja" => ["量子みち", "数の美が待つ", "ちから来る"],
+        _ => ["Quantum paths unfold,", "Mathematical grace waits—", "Information near"],
+    };
+    lines.iter().map(|line| line.to_string()).collect()
+}
+
+/// Every generation mechanism a fortune can report
+const MECHANISMS: &[&str] = &[
+    "tri-loop correlation matrix convergence",
+    "semantic closure boundary optimization",
+    "hofstadter coefficient recursive analysis",
+    "expander graph spectral gap resonance",
+    "strange loop paradox resolution synthesis",
+];
+
+/// One-sentence explanations for visitors, keyed by mechanism
+const MECHANISM_EXPLANATIONS: &[(&str, &str)] = &[
+    ("tri-loop correlation matrix convergence", "The three generating loops are moving in step, so the fortune reflects what they agree on."),
+    ("semantic closure boundary optimization", "The system's meaning has nearly closed in on itself, so the fortune leans on that completeness."),
+    ("hofstadter coefficient recursive analysis", "The system is strongly referring back to itself, so the fortune plays with self-reference."),
+    ("expander graph spectral gap resonance", "Information is spreading quickly and evenly through the network behind the oracle."),
+    ("strange loop paradox resolution synthesis", "Several self-referential loops are active, and the fortune resolves the paradox they form."),
+];
+
+/// The mechanism's URL slug, e.g. `hofstadter-coefficient-recursive-analysis`
+fn mechanism_slug(mechanism: &str) -> String {
+    mechanism.replace(' ', "-")
+}
+
+/// Plain-language explanation of a mechanism, if it has one
+fn mechanism_explanation(mechanism: &str) -> Option<&'static str> {
+    MECHANISM_EXPLANATIONS
+        .iter()
+        .find(|(name, _)| *name == mechanism)
+        .map(|(_, explanation)| *explanation)
+}
+
+fn select_generation_mechanism(metrics: &InformationForceMetrics, seed: Option<u64>) -> String {
+    match seed {
+        // Salt the seed so the mechanism stream is independent of the haiku stream
+        Some(seed) => {
+            let mechanisms = unique_mechanisms();
+            mechanisms[SplitMix64::new(seed ^ MECHANISM_SEED_SALT).pick(mechanisms.len())].to_string()
+        }
+        None => MECHANISMS[dominant_mechanism(metrics)].to_string(),
+    }
+}
+
+/// `MECHANISMS` without repeats, checked once on first use; duplicates are logged
+/// and dropped so they can't skew seeded selection
+fn unique_mechanisms() -> &'static [&'static str] {
+    static UNIQUE: OnceLock<Vec<&'static str>> = OnceLock::new();
+
+    UNIQUE.get_or_init(|| {
+        let mut seen = HashSet::new();
+        MECHANISMS
+            .iter()
+            .copied()
+            .filter(|mechanism| {
+                let unique = seen.insert(*mechanism);
+                if !unique {
+                    log!(warn, "Duplicate mechanism {:?} ignored", mechanism);
+                }
+                unique
+            })
+            .collect()
+    })
+}
+
+/// Typical range of the metric each mechanism is named after, index-aligned with
+/// `MECHANISMS`: correlation, closure, Hofstadter coefficient, spectral gap, strange loops
+const MECHANISM_METRIC_RANGES: [(f64, f64); 5] = [
+    (0.9, 1.0),
+    (0.7, 1.0),
+    (1.0, 1.5),
+    (0.0, SPECTRAL_GAP_SCALE),
+    (MIN_STRANGE_LOOPS as f64, 7.0),
+];
+
+/// Index of the mechanism whose metric sits highest within its typical range.
+/// Ties go to the earlier mechanism, so the choice is deterministic
+fn dominant_mechanism(metrics: &InformationForceMetrics) -> usize {
+    let values = [
+        metrics.correlation_strength,
+        metrics.semantic_closure,
+        metrics.hofstadter_coefficient,
+        metrics.spectral_gap,
+        f64::from(metrics.strange_loops),
+    ];
+    
+    values
+        .iter()
+        .zip(MECHANISM_METRIC_RANGES)
+        .map(|(value, (low, high))| (value - low) / (high - low))
+        .enumerate()
+        .fold((0, f64::NEG_INFINITY), |best, (index, score)| if score > best.1 { (index, score) } else { best })
+        .0
+}
+
+/// Salt mixed into the seed for mechanism selection
+const MECHANISM_SEED_SALT: u64 = 0x6d65_6368_616e_6973;
+
+/// SplitMix64 PRNG - tiny, fast, and fully deterministic for seeded fortunes
+struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    fn new(seed: u64) -> Self {
+        SplitMix64 { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform-enough index in `0..len` (len must be non-zero)
+    fn pick(&mut self, len: usize) -> usize {
+        (self.next_u64() % len as u64) as usize
+    }
+}
+
+/// Seed of the shuffled deck the daily fortune is dealt from
+const DAILY_DECK_SEED: u64 = 0x6461_696c_7964_6563;
+
+/// Slot in `0..len` for `day`: a fixed shuffle of the slots, dealt one per day and
+/// restarted when exhausted, so neighbouring days always differ (when `len > 1`)
+fn daily_slot(day: u64, len: usize) -> usize {
+    let mut deck: Vec<usize> = (0..len).collect();
+    let mut rng = SplitMix64::new(DAILY_DECK_SEED);
+    for i in (1..len).rev() {
+        deck.swap(i, rng.pick(i + 1));
+    }
+    deck[(day % len as u64) as usize]
+}
+
+fn get_current_timestamp() -> u64 {
+    // Milliseconds since the Unix epoch from the host wall clock
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_millis() as u64,
+        Err(_) => {
+            log!(warn, "System clock is before the Unix epoch - reporting timestamp 0");
+            0
+        }
+    }
+}
+
+/// Format days since the Unix epoch as a proleptic Gregorian `YYYY-MM-DD` date
+fn format_date(days_since_epoch: u64) -> String {
+    // Civil-from-days: shift to eras starting on 0000-03-01 so leap days fall last
+    let days = days_since_epoch as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Format Unix epoch milliseconds as an RFC 3339 UTC timestamp (`2025-08-30T18:04:05.123Z`)
+fn format_timestamp_iso(millis: u64) -> String {
+    let millis_of_day = millis % MILLIS_PER_DAY;
+    let seconds_of_day = millis_of_day / 1000;
+    
+    format!(
+        "{}T{:02}:{:02}:{:02}.{:03}Z",
+        format_date(millis / MILLIS_PER_DAY),
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60,
+        millis_of_day % 1000,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    
+    thread_local! {
+        /// Spin variables as seen by `variable_or` on this test's thread
+        static VARIABLES: RefCell<HashMap<String, String>> = RefCell::new(HashMap::new());
+    }
+    
+    pub(super) fn variable(name: &str) -> Option<String> {
+        VARIABLES.with(|variables| variables.borrow().get(name).cloned())
+    }
+    
+    fn set_variable(name: &str, value: &str) {
+        VARIABLES.with(|variables| variables.borrow_mut().insert(name.to_string(), value.to_string()));
+    }
+    
+    thread_local! {
+        /// State files parsed (rather than answered from the memo) on this test's thread
+        static STATE_PARSES: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+    
+    pub(super) fn count_state_parse() {
+        STATE_PARSES.with(|parses| parses.set(parses.get() + 1));
+    }
+    
+    thread_local! {
+        /// The default KV store as seen from this test's thread
+        static KV: RefCell<BTreeMap<String, Vec<u8>>> = const { RefCell::new(BTreeMap::new()) };
+    }
+    
+    /// Stands in for `spin_sdk::key_value::Store`, with the subset of its API we use
+    pub(super) struct MemoryStore;
+    
+    impl MemoryStore {
+        pub(super) fn open_default() -> Result<Self, std::convert::Infallible> {
+            Ok(MemoryStore)
+        }
+        
+        pub(super) fn get(&self, key: &str) -> Result<Option<Vec<u8>>, std::convert::Infallible> {
+            Ok(KV.with(|kv| kv.borrow().get(key).cloned()))
+        }
+        
+        pub(super) fn set(&self, key: &str, value: &[u8]) -> Result<(), std::convert::Infallible> {
+            KV.with(|kv| kv.borrow_mut().insert(key.to_string(), value.to_vec()));
+            Ok(())
+        }
+        
+        pub(super) fn delete(&self, key: &str) -> Result<(), std::convert::Infallible> {
+            KV.with(|kv| kv.borrow_mut().remove(key));
+            Ok(())
+        }
+        
+        pub(super) fn get_keys(&self) -> Result<Vec<String>, std::convert::Infallible> {
+            Ok(KV.with(|kv| kv.borrow().keys().cloned().collect()))
+        }
+        
+        /// `wasi:keyvalue/atomics.increment`, with counters stored as decimal text
+        pub(super) fn increment(&self, key: &str, delta: i64) -> i64 {
+            KV.with(|kv| {
+                let mut kv = kv.borrow_mut();
+                let current: i64 = kv
+                    .get(key)
+                    .and_then(|value| std::str::from_utf8(value).ok())
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or(0);
+                kv.insert(key.to_string(), (current + delta).to_string().into_bytes());
+                current + delta
+            })
+        }
+    }
+    
+    /// State held in memory, so the metrics and tri-loop paths run without fixture files
+    struct MemoryStateSource {
+        state: Option<LoopState>,
+        age: Option<Duration>,
+    }
+    
+    impl MemoryStateSource {
+        fn from_json(json: &str) -> Self {
+            MemoryStateSource { state: Some(serde_json::from_str(json).unwrap()), age: Some(Duration::ZERO) }
+        }
+        
+        fn missing() -> Self {
+            MemoryStateSource { state: None, age: None }
+        }
+    }
+    
+    impl StateSource for MemoryStateSource {
+        fn load(&self) -> Result<Option<LoopState>, StateFileError> {
+            Ok(self.state.clone())
+        }
+        
+        fn age(&self) -> Option<Duration> {
+            self.age
+        }
+    }
+    
+    /// Records what it is asked to publish instead of connecting to a bus
+    #[derive(Default)]
+    struct MockPublisher {
+        published: RefCell<Vec<Vec<Publication>>>,
+    }
+    
+    impl FortunePublisher for MockPublisher {
+        fn publish(&self, fortunes: &[Publication]) {
+            self.published.borrow_mut().push(fortunes.to_vec());
+        }
+    }
+    
+    /// A `topos_dir` holding `current_loop_state.json` with `state`, selected for this thread
+    fn fixture_topos(name: &str, state: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("zeldar-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("current_loop_state.json"), state).unwrap();
+        set_variable("topos_dir", dir.to_str().unwrap());
+        dir
+    }
+    
+    fn sample_metrics() -> InformationForceMetrics {
+        InformationForceMetrics {
+            semantic_closure: 0.9252,
+            strange_loops: 4,
+            hofstadter_coefficient: 1.084,
+            spectral_gap: 9.26,
+            correlation_strength: 0.98,
+            threshold_exceeded: true,
+            phi: Some(3.252),
+            quantum_entropy: Some(0.926),
+            loop_iteration: Some(7),
+            raw_semantic_closure: None,
+            raw_correlation_strength: None,
+            source: Some(MetricSource::StateFile),
+        }
+    }
+    
+    fn sample_fortune() -> FortuneResponse {
+        FortuneResponse {
+            id: "0123abcd-0000-0000-0000-000000000000".to_string(),
+            haiku: vec![
+                "Quantum paths unfold,".to_string(),
+                "Mathematical grace waits—".to_string(),
+                "Information near".to_string(),
+            ],
+            mechanism: MECHANISMS[0].to_string(),
+            mechanism_explanation: None,
+            information_dynamics: sample_metrics(),
+            timestamp: 1_756_577_045_123,
+            timestamp_iso: None,
+            tri_loop_status: TriLoopStatus {
+                mcp_active: true,
+                gemini_connected: true,
+                codex_generating: true,
+                correlation_detected: true,
+                detection_method: "heartbeat".to_string(),
+                correlation_reason: Some("ok".to_string()),
+            },
+            syllables: None,
+            theme: Some("desert".to_string()),
+            theme_matched: None,
+            date: None,
+            lang: DEFAULT_LANGUAGE.to_string(),
+            note: None,
+            explanation: None,
+            request_nonce: None,
+            rare: false,
+            short_code: None,
+        }
+    }
+    
+    #[test]
+    fn ticket_fits_banner_at_minimum_width() {
+        for width in [0, MIN_TICKET_WIDTH - 1, MIN_TICKET_WIDTH] {
+            let ticket = render_ticket(&sample_fortune(), width);
+            for line in ticket.lines() {
+                assert_eq!(display_width(line), MIN_TICKET_WIDTH, "{:?}", line);
+            }
+            assert!(ticket.contains("☀ ZELDAR ORACLE ☀"));
+        }
+    }
+    
+    #[test]
+    fn clock_is_real_and_monotonic() {
+        let first = get_current_timestamp();
+        std::thread::sleep(Duration::from_millis(5));
+        let second = get_current_timestamp();
+        assert!(first > 0);
+        assert!(second >= first);
+    }
+    
+    fn get(uri: &str) -> Request {
+        Request::builder().method(Method::Get).uri(uri).build()
+    }
+    
+    fn post(uri: &str, body: &str) -> Request {
+        Request::builder()
+            .method(Method::Post)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(body.as_bytes().to_vec())
+            .build()
+    }
+    
+    /// A successful response's JSON body
+    fn json_body(response: &Response) -> serde_json::Value {
+        assert!((200..300).contains(response.status()), "status {}: {}", response.status(), String::from_utf8_lossy(response.body()));
+        serde_json::from_slice(response.body()).unwrap()
+    }
+    
+    #[test]
+    fn routes_ignore_query_and_trailing_slash() {
+        for uri in ["/api/oracle/fortune", "/api/oracle/fortune?foo=bar", "/api/oracle/fortune/"] {
+            let req = get(uri);
+            let (path, _) = split_path_and_query(&req);
+            assert_eq!(Route::from_path(path), Route::Fortune, "{}", uri);
+        }
+        
+        let req = get("/api/oracle/fortune?seed=42&theme=desert%20sun");
+        let (_, query) = split_path_and_query(&req);
+        let params = parse_query(query);
+        assert_eq!(params.get("seed").map(String::as_str), Some("42"));
+        assert_eq!(params.get("theme").map(String::as_str), Some("desert sun"));
+        
+        assert_eq!(Route::from_path("/api/oracle/fortune/abc"), Route::StoredFortune("abc"));
+        assert_eq!(Route::from_path("/api/oracle/fortune/abc/qr"), Route::FortuneQr("abc"));
+        assert_eq!(Route::from_path("/nowhere"), Route::Unknown);
+    }
+    
+    #[test]
+    fn haiku_splits_on_real_and_escaped_newlines() {
+        let expected = vec!["Loops fold into loops".to_string(), "the oracle counts itself".to_string(), "sand remembers all".to_string()];
+        let real = "Loops fold into loops\nthe oracle counts itself  \n\nsand remembers all\n";
+        let escaped = r"Loops fold into loops\nthe oracle counts itself\nsand remembers all";
+        assert_eq!(split_haiku_lines(real), expected);
+        assert_eq!(split_haiku_lines(escaped), expected);
+    }
+    
+    #[test]
+    fn prometheus_output_is_valid_exposition() {
+        let text = render_prometheus(&sample_metrics());
+        let mut typed = HashSet::new();
+        let mut samples = HashMap::new();
+        for line in text.lines() {
+            if let Some(comment) = line.strip_prefix("# ") {
+                let mut parts = comment.splitn(3, ' ');
+                let (kind, name, rest) = (parts.next().unwrap(), parts.next().unwrap(), parts.next().unwrap());
+                match kind {
+                    "HELP" => assert!(!rest.is_empty()),
+                    "TYPE" => {
+                        assert_eq!(rest, "gauge");
+                        typed.insert(name.to_string());
+                    }
+                    other => panic!("unexpected comment {}", other),
+                }
+            } else {
+                let (name, value) = line.split_once(' ').expect("sample line");
+                assert!(name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'), "{}", name);
+                assert!(typed.contains(name), "{} has no TYPE before it", name);
+                samples.insert(name.to_string(), value.parse::<f64>().expect("numeric value"));
+            }
+        }
+        
+        for series in [
+            "oracle_semantic_closure",
+            "oracle_strange_loops",
+            "oracle_hofstadter_coefficient",
+            "oracle_spectral_gap",
+            "oracle_correlation_strength",
+            "oracle_threshold_exceeded",
+        ] {
+            assert!(samples.contains_key(series), "missing {}", series);
+        }
+        assert_eq!(samples["oracle_threshold_exceeded"], 1.0);
+    }
+    
+    #[test]
+    fn stored_fortune_round_trips() {
+        let fortune = sample_fortune();
+        let stored = serde_json::to_vec(&fortune).unwrap();
+        let loaded: FortuneResponse = serde_json::from_slice(&stored).unwrap();
+        assert_eq!(serde_json::to_value(&loaded).unwrap(), serde_json::to_value(&fortune).unwrap());
+        
+        // Seeded ids are reproducible and depend on every input
+        let id = fortune_id(42, 0, &fortune.haiku, &fortune.mechanism, None);
+        assert_eq!(id, fortune_id(42, 0, &fortune.haiku, &fortune.mechanism, None));
+        assert_ne!(id, fortune_id(42, 1, &fortune.haiku, &fortune.mechanism, None));
+        assert_ne!(id, fortune_id(42, 0, &fortune.haiku, &fortune.mechanism, Some("abc")));
+        assert_eq!(fortune_key(&id), format!("fortune:{}", id));
+    }
+    
+    fn allow_origin(origin: Option<&str>, allowed: &str) -> Option<String> {
+        let mut req = get("/api/oracle/fortune");
+        if let Some(origin) = origin {
+            req.set_header("origin", origin);
+        }
+        let headers = cors_headers_for(&req, allowed);
+        assert!(headers.contains(&("vary", "Origin".to_string())));
+        headers
+            .into_iter()
+            .find(|(name, _)| *name == "access-control-allow-origin")
+            .map(|(_, value)| value)
+    }
+    
+    #[test]
+    fn cors_echoes_only_listed_origins() {
+        let allowed = "https://zeldar.camp, https://playa.example";
+        assert_eq!(allow_origin(Some("https://playa.example"), allowed).as_deref(), Some("https://playa.example"));
+        assert_eq!(allow_origin(Some("https://evil.example"), allowed), None);
+        assert_eq!(allow_origin(None, allowed), None);
+        assert_eq!(allow_origin(Some("https://anyone.example"), "*").as_deref(), Some("*"));
+        
+        let preflight = handle_cors_preflight(Route::Fortune).unwrap();
+        let header = |name: &str| preflight.header(name).and_then(|value| value.as_str()).map(str::to_string);
+        assert_eq!(header("access-control-max-age"), Some(CORS_MAX_AGE_SECS.to_string()));
+        assert_eq!(header("access-control-allow-methods").as_deref(), Some("GET, HEAD, POST, OPTIONS"));
+    }
+    
+    #[test]
+    fn request_over_the_limit_gets_429() {
+        let per_minute = 5.0;
+        let now = 1_000_000;
+        let mut bucket = None;
+        for _ in 0..5 {
+            let (next, retry_after) = draw_token(bucket, now, per_minute);
+            assert_eq!(retry_after, None);
+            bucket = Some(next);
+        }
+        let (next, retry_after) = draw_token(bucket, now, per_minute);
+        assert_eq!(retry_after, Some(12));
+        
+        // A token is back once the refill interval has passed
+        let (_, retry_after) = draw_token(Some(next), now + 12_000, per_minute);
+        assert_eq!(retry_after, None);
+        
+        let response = OracleError::RateLimited { retry_after_secs: 12 }.into_response();
+        assert_eq!(*response.status(), 429);
+        assert_eq!(response.header("retry-after").and_then(|value| value.as_str()), Some("12"));
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["error"]["code"], "rate_limited");
+    }
+    
+    #[test]
+    fn metrics_etag_revalidates_to_304() {
+        let body = serde_json::to_string(&sample_metrics()).unwrap();
+        let first = cacheable_json(&get("/api/information-dynamics/metrics"), body.clone());
+        assert_eq!(*first.status(), 200);
+        assert_eq!(first.body(), body.as_bytes());
+        assert_eq!(first.header("cache-control").and_then(|value| value.as_str()), Some("max-age=2"));
+        let etag = first.header("etag").and_then(|value| value.as_str()).unwrap().to_string();
+        assert!(etag.starts_with("W/\""));
+        
+        let mut revalidate = get("/api/information-dynamics/metrics");
+        revalidate.set_header("if-none-match", etag.as_str());
+        let second = cacheable_json(&revalidate, body.clone());
+        assert_eq!(*second.status(), 304);
+        assert!(second.body().is_empty());
+        assert_eq!(second.header("etag").and_then(|value| value.as_str()), Some(etag.as_str()));
+        
+        let changed = cacheable_json(&revalidate, body.replace("0.9252", "0.9253"));
+        assert_eq!(*changed.status(), 200);
+    }
+    
+    #[test]
+    fn json_log_lines_parse() {
+        set_current_request_id("req-123");
+        let mut out = Vec::new();
+        write_log(&mut out, LogFormat::Json, "warn", format_args!("quote \" and\nnewline {}", 42));
+        write_log(&mut out, LogFormat::Json, "info", format_args!("second"));
+        
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<serde_json::Value> = text.lines().map(|line| serde_json::from_str(line).expect("one JSON object per line")).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["level"], "warn");
+        assert_eq!(lines[0]["msg"], "quote \" and\nnewline 42");
+        assert_eq!(lines[0]["request_id"], "req-123");
+        assert!(lines[0]["ts"].as_str().is_some_and(|ts| ts.ends_with('Z')));
+        
+        let mut out = Vec::new();
+        write_log(&mut out, LogFormat::Text, "info", format_args!("hello"));
+        assert_eq!(String::from_utf8(out).unwrap(), "[req-123] INFO hello\n");
+    }
+    
+    #[test]
+    fn full_state_drives_metrics() {
+        let source = MemoryStateSource::from_json(r#"{"information-dynamics_phi": 3.252, "quantum_entropy": 0.926, "loop_iteration": 9}"#);
+        let metrics = read_information_dynamics_metrics(&source, MetricsMode::Auto).unwrap();
+        assert_eq!(metrics.source, Some(MetricSource::StateFile));
+        assert_eq!(metrics.phi, Some(3.252));
+        assert!((metrics.semantic_closure - 0.9252).abs() < 1e-9);
+        assert_eq!(metrics.correlation_strength, STATE_CORRELATION);
+        assert!(metrics.threshold_exceeded);
+        
+        set_variable("consciousness_threshold", "0.95");
+        assert!(!read_information_dynamics_metrics(&source, MetricsMode::Auto).unwrap().threshold_exceeded);
+        
+        let tri_loop = assess_tri_loop_system(&source);
+        assert!(tri_loop.correlation_detected);
+        assert_eq!(tri_loop.correlation_reason.as_deref(), Some("ok"));
+    }
+    
+    #[test]
+    fn partial_state_never_crosses_the_threshold() {
+        let source = MemoryStateSource::from_json(r#"{"information-dynamics_phi": 9.9, "quantum_entropy": "garbled"}"#);
+        let metrics = read_information_dynamics_metrics(&source, MetricsMode::Auto).unwrap();
+        assert_eq!(metrics.source, Some(MetricSource::StateFile));
+        assert_eq!(metrics.semantic_closure, 1.0);
+        assert_eq!(metrics.quantum_entropy, None);
+        assert!(!metrics.threshold_exceeded);
+        
+        let tri_loop = assess_tri_loop_system(&source);
+        assert!(!tri_loop.correlation_detected);
+        assert_eq!(tri_loop.correlation_reason.as_deref(), Some("state_incomplete"));
+    }
+    
+    #[test]
+    fn missing_state_falls_back_to_simulation() {
+        let source = MemoryStateSource::missing();
+        let metrics = read_information_dynamics_metrics(&source, MetricsMode::Auto).unwrap();
+        assert_eq!(metrics.source, Some(MetricSource::Simulation));
+        assert_eq!(metrics.phi, None);
+        
+        let tri_loop = assess_tri_loop_system(&source);
+        assert_eq!(tri_loop.correlation_reason.as_deref(), Some("state_file_missing"));
+    }
+    
+    /// Rasterise `render_qr`'s SVG (one `M x,y h1v1h-1z` square per dark module) and decode it
+    fn decode_qr_svg(svg: &[u8]) -> String {
+        const SCALE: usize = 4;
+        
+        let svg = std::str::from_utf8(svg).unwrap();
+        let extent: usize = svg.split("viewBox=\"0 0 ").nth(1).unwrap().split(' ').next().unwrap().parse().unwrap();
+        let path = svg.split(" d=\"").nth(1).unwrap().split('"').next().unwrap();
+        let mut dark = vec![false; extent * extent];
+        for square in path.split('M').filter(|square| !square.is_empty()) {
+            let (x, rest) = square.split_once(',').unwrap();
+            let y = rest.split('h').next().unwrap();
+            dark[y.parse::<usize>().unwrap() * extent + x.parse::<usize>().unwrap()] = true;
+        }
+        
+        let pixels = extent * SCALE;
+        let mut image = rqrr::PreparedImage::prepare_from_greyscale(pixels, pixels, |x, y| {
+            if dark[(y / SCALE) * extent + x / SCALE] { 0 } else { 255 }
+        });
+        let grids = image.detect_grids();
+        assert_eq!(grids.len(), 1);
+        grids[0].decode().unwrap().1
+    }
+    
+    #[test]
+    fn qr_decodes_to_the_permalink() {
+        let permalink = format!("{}/api/oracle/fortune/{}", DEFAULT_PUBLIC_BASE_URL, sample_fortune().id);
+        assert_eq!(decode_qr_svg(&render_qr(&permalink)), permalink);
+        
+        // Short and long payloads exercise small and multi-block versions
+        for url in ["https://z.io/f/Ab3", &format!("https://zeldar.example/{}", "x".repeat(300)), &"y".repeat(MAX_QR_PAYLOAD)] {
+            assert_eq!(decode_qr_svg(&render_qr(url)), url);
+        }
+        
+        // Too long for any version once the base URL is included
+        set_variable("rare_fortune_chance", "0");
+        let id = json_body(&route_request(&get("/api/oracle/fortune")))["id"].as_str().unwrap().to_string();
+        set_variable("public_base_url", &"x".repeat(MAX_QR_PAYLOAD));
+        assert_eq!(*route_request(&get(&format!("/api/oracle/fortune/{}/qr", id))).status(), 500);
+    }
+    
+    #[test]
+    fn gzip_responses_decompress_to_the_original() {
+        use std::io::Read;
+        
+        let body = serde_json::to_string(&vec![sample_metrics(); 8]).unwrap();
+        let respond = |body: &str| Response::builder().status(200).header("content-type", "application/json").body(body.to_string()).build();
+        let mut req = get("/api/information-dynamics/history");
+        req.set_header("accept-encoding", "br, gzip;q=0.8");
+        
+        let response = compress_response(&req, respond(&body));
+        assert_eq!(response.header("content-encoding").and_then(|value| value.as_str()), Some("gzip"));
+        assert_eq!(response.header("vary").and_then(|value| value.as_str()), Some("Accept-Encoding"));
+        let mut decompressed = String::new();
+        flate2::read::GzDecoder::new(response.body()).read_to_string(&mut decompressed).unwrap();
+        assert_eq!(decompressed, body);
+        
+        // Tiny bodies and clients without gzip get the body untouched
+        let tiny = compress_response(&req, respond("{}"));
+        assert!(tiny.header("content-encoding").is_none());
+        let plain = compress_response(&get("/api/information-dynamics/history"), respond(&body));
+        assert!(plain.header("content-encoding").is_none());
+        assert_eq!(plain.body(), body.as_bytes());
+    }
+    
+    
+    #[test]
+    fn state_signature_matches_rfc_4231_vectors() {
+        let signature = std::env::temp_dir().join(format!("zeldar-rfc4231-{}.sig", std::process::id()));
+        let cases: [(&[u8], &[u8], &str); 3] = [
+            (&[0x0b; 20], b"Hi There", "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"),
+            (b"Jefe", b"what do ya want for nothing?", "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"),
+            (
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First",
+                "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+            ),
+        ];
+        for (key, data, mac) in cases {
+            std::fs::write(&signature, format!("{}\n", mac)).unwrap();
+            assert!(state_signature_valid(key, data, &signature));
+            assert!(!state_signature_valid(key, b"tampered", &signature));
+        }
+        std::fs::remove_file(&signature).unwrap();
+    }
+    
+    #[test]
+    fn signed_state_file_is_verified() {
+        let path = std::env::temp_dir().join(format!("zeldar-signed-{}.json", std::process::id()));
+        let body = r#"{"information-dynamics_phi": 0.7}"#;
+        std::fs::write(&path, body).unwrap();
+        
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(body.as_bytes());
+        let hex: String = mac.finalize().into_bytes().iter().map(|byte| format!("{:02x}", byte)).collect();
+        
+        // No key: the sidecar is never consulted
+        assert!(parse_state_file(&path, "").is_ok());
+        assert_eq!(parse_state_file(&path, "secret").unwrap_err(), StateFileError::SignatureInvalid);
+        
+        std::fs::write(signature_path(&path), &hex).unwrap();
+        assert_eq!(parse_state_file(&path, "secret").unwrap().phi, Some(0.7));
+        assert_eq!(parse_state_file(&path, "other").unwrap_err(), StateFileError::SignatureInvalid);
+        
+        std::fs::write(signature_path(&path), "not hex").unwrap();
+        assert_eq!(parse_state_file(&path, "secret").unwrap_err(), StateFileError::SignatureInvalid);
+        
+        std::fs::remove_file(signature_path(&path)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    
+    #[test]
+    fn metrics_mode_picks_the_source() {
+        for (value, mode) in [("file", MetricsMode::File), (" Simulation ", MetricsMode::Simulation), ("bogus", MetricsMode::Auto)] {
+            set_variable("metrics_mode", value);
+            assert_eq!(metrics_mode(), mode);
+        }
+        
+        let full = MemoryStateSource::from_json(r#"{"information-dynamics_phi": 3.252, "quantum_entropy": 0.926, "loop_iteration": 9}"#);
+        assert_eq!(read_information_dynamics_metrics(&full, MetricsMode::File).unwrap().source, Some(MetricSource::StateFile));
+        assert!(matches!(
+            read_information_dynamics_metrics(&MemoryStateSource::missing(), MetricsMode::File),
+            Err(OracleError::Unavailable(_))
+        ));
+        
+        set_variable("metrics_mode", "simulation");
+        assert_eq!(state_source().age(), None);
+        let metrics = read_information_dynamics_metrics(state_source(), metrics_mode()).unwrap();
+        assert_eq!(metrics.source, Some(MetricSource::Simulation));
+    }
+    
+    
+    #[test]
+    fn webhooks_wait_in_the_queue_until_taken() {
+        queue_webhook("https://hooks.example/a", b"{}".to_vec());
+        queue_webhook("https://hooks.example/b", b"[]".to_vec());
+        assert_eq!(
+            take_pending_webhooks(),
+            vec![
+                ("https://hooks.example/a".to_string(), b"{}".to_vec()),
+                ("https://hooks.example/b".to_string(), b"[]".to_vec()),
+            ]
+        );
+        assert!(take_pending_webhooks().is_empty());
+    }
+    
+    
+    #[test]
+    fn internal_errors_hide_the_cause_chain() {
+        let cause = anyhow::anyhow!("kv key stats:total unreadable").context("reading /topos/current_loop_state.json");
+        let response = OracleError::from(cause).into_response();
+        assert_eq!(*response.status(), 500);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["error"]["code"], "internal_error");
+        assert_eq!(body["error"]["message"], INTERNAL_ERROR_MESSAGE);
+        
+        // Our own errors pass through with their status and message
+        let response = OracleError::from(anyhow::Error::from(OracleError::BadRequest("Invalid limit: x".to_string()))).into_response();
+        assert_eq!(*response.status(), 400);
+    }
+    
+    
+    #[test]
+    fn health_tolerates_a_briefly_missing_state_file() {
+        let now = 10_000_000;
+        let minute = Duration::from_secs(60);
+        
+        // Present: healthy while fresh, and the write time is remembered
+        assert_eq!(state_health(Some(minute), None, now), (true, Some(now - 60_000)));
+        assert_eq!(state_health(Some(minute), Some(now - 60_400), now), (true, None));
+        assert_eq!(state_health(Some(minute * 6), Some(now - 360_000), now), (false, None));
+        
+        // Missing: fine until five minutes after the last write
+        assert_eq!(state_health(None, Some(now - 60_000), now), (true, None));
+        assert_eq!(state_health(None, Some(now - 300_000), now), (false, None));
+        
+        // Never seen: the grace period starts now
+        assert_eq!(state_health(None, None, now), (true, Some(now)));
+        assert!(!state_health(None, Some(now), now + 300_000).0);
+    }
+    
+    
+    #[test]
+    fn explain_reports_query_metrics_as_query() {
+        let params = GenerationParams { live: false, ..GenerationParams::default() };
+        let choice = HaikuChoice { lines: Vec::new(), theme: None, origin: HaikuOrigin::Standard, pool_index: None };
+        
+        let query: HashMap<String, String> = [("phi".to_string(), "3.252".to_string())].into();
+        let metrics = supplied_phi_metrics(&query).unwrap().unwrap();
+        assert_eq!(metrics.source, Some(MetricSource::Query));
+        assert_eq!(explain_selection(&params, &metrics, &choice).metrics_source, "query");
+        
+        let posted = InformationForceMetrics { source: None, ..metrics };
+        assert_eq!(explain_selection(&params, &posted, &choice).metrics_source, "request_body");
+    }
+    
+    
+    #[test]
+    fn generated_fortunes_are_retrievable_by_id() {
+        let fortune = json_body(&route_request(&get("/api/oracle/fortune")));
+        let id = fortune["id"].as_str().unwrap();
+        
+        let stored = json_body(&route_request(&get(&format!("/api/oracle/fortune/{}", id))));
+        assert_eq!(stored["haiku"], fortune["haiku"]);
+        assert_eq!(stored["mechanism"], fortune["mechanism"]);
+        
+        assert_eq!(*route_request(&get("/api/oracle/fortune/no-such-id")).status(), 404);
+    }
+    
+    
+    #[test]
+    fn post_body_parameters_drive_generation() {
+        set_variable("rare_fortune_chance", "0");
+        let single = json_body(&route_request(&post("/api/information-dynamics/generate", "")));
+        assert!(single.is_object());
+        
+        let body = r#"{"theme":"desert","count":"3","unknown_key":"ignored"}"#;
+        let bundle = json_body(&route_request(&post("/api/information-dynamics/generate", body)));
+        let fortunes = bundle.as_array().unwrap();
+        assert_eq!(fortunes.len(), 3);
+        for fortune in fortunes {
+            assert_eq!(fortune["theme"], "desert");
+        }
+        
+        let response = route_request(&post("/api/information-dynamics/generate", r#"{"count":"abc"}"#));
+        assert_eq!(*response.status(), 400);
+        let error: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(error["error"]["code"], "bad_request");
+    }
+    
+    
+    #[test]
+    fn syllable_counts_for_known_words() {
+        for (word, syllables) in [
+            ("table", 2),
+            ("make", 1),
+            ("boxes", 2),
+            ("wanted", 2),
+            ("loved", 1),
+            ("whole", 1),
+            ("circles", 2),
+            ("kindled", 2),
+            ("echoes", 2),
+            ("agree", 2),
+            ("information", 4),
+        ] {
+            assert_eq!(count_syllables(word), syllables, "{}", word);
+        }
+        assert_eq!(count_syllables("Quantum paths unfold,"), 5);
+    }
+    
+    /// Every compiled English line passes the validator. Spanish and Japanese are exempt:
+    /// the heuristic counts English vowel groups, and Japanese haiku count morae
+    #[test]
+    fn compiled_english_haiku_are_5_7_5() {
+        let to_lines = |lines: &[&str]| lines.iter().map(|line| line.to_string()).collect::<Vec<_>>();
+        let mut haiku: Vec<Vec<String>> = HAIKU_TEMPLATES.iter().map(|(_, lines)| to_lines(lines)).collect();
+        haiku.extend(NEUTRAL_DESERT_TEMPLATES.iter().filter(|(lang, _)| *lang == "en").map(|(_, lines)| to_lines(lines)));
+        haiku.extend(RARE_HAIKU.iter().map(|lines| to_lines(lines)));
+        haiku.push(generate_standard_haiku("en"));
+        for lines in &haiku {
+            assert_eq!(haiku_structure_error(lines), None);
+        }
+        
+        for line in ACROSTIC_FIVE {
+            assert_eq!(count_syllables(line), 5, "{}", line);
+        }
+        for line in ACROSTIC_SEVEN {
+            assert_eq!(count_syllables(line), 7, "{}", line);
+        }
+    }
+    
+    #[test]
+    fn malformed_oracle_haiku_falls_back_to_a_template() {
+        let params = GenerationParams::default();
+        let generate = |source: &MemoryStateSource| {
+            generate_information_dynamics_haiku(&sample_metrics(), &params, source, &[], &[], 0, &mut HashSet::new())
+        };
+        
+        let valid = MemoryStateSource::from_json(r#"{"haiku_content": "Hidden paths reveal\nStrange loops embrace paradox—\nMagic lives in doubt"}"#);
+        let choice = generate(&valid);
+        assert_eq!(choice.origin, HaikuOrigin::Oracle);
+        assert_eq!(choice.lines[1], "Strange loops embrace paradox—");
+        
+        let malformed = MemoryStateSource::from_json(r#"{"haiku_content": "Too short\nStrange loops embrace paradox—\nMagic lives in doubt"}"#);
+        let choice = generate(&malformed);
+        assert_eq!(choice.origin, HaikuOrigin::Template);
+        assert!(is_valid_haiku(&choice.lines));
+    }
+    
+    
+    #[test]
+    fn splitmix64_matches_reference_outputs() {
+        let mut rng = SplitMix64::new(0);
+        assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+        assert_eq!(rng.next_u64(), 0x06c4_5d18_8009_454f);
+    }
+    
+    #[test]
+    fn same_seed_gives_the_same_fortune() {
+        let first = json_body(&route_request(&get("/api/oracle/fortune?seed=1234")));
+        let second = json_body(&route_request(&get("/api/oracle/fortune?seed=1234")));
+        assert_eq!(first["haiku"], second["haiku"]);
+        assert_eq!(first["mechanism"], second["mechanism"]);
+        assert_eq!(first["id"], second["id"]);
+    }
+    
+    
+    #[test]
+    fn topos_dir_variable_moves_the_state_files() {
+        assert_eq!(topos_path("current_loop_state.json"), Path::new("../.topos/current_loop_state.json"));
+        set_variable("topos_dir", "/srv/oracle/.topos");
+        assert_eq!(topos_path("current_loop_state.json"), Path::new("/srv/oracle/.topos/current_loop_state.json"));
+    }
+    
+    
+    #[test]
+    fn post_routing_is_path_aware() {
+        let fortune = json_body(&route_request(&post("/api/information-dynamics/generate", "{}")));
+        assert_eq!(fortune["haiku"].as_array().unwrap().len(), 3);
+        
+        let response = route_request(&post("/api/no/such/path", "{}"));
+        assert_eq!(*response.status(), 404);
+        let error: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(error["error"]["code"], "not_found");
+    }
+    
+    
+    #[test]
+    fn accept_text_plain_gets_bare_haiku() {
+        let with_accept = |accept: &str| {
+            let mut request = get("/api/oracle/fortune?seed=7");
+            request.set_header("accept", accept);
+            route_request(&request)
+        };
+        
+        for accept in ["application/json", "*/*"] {
+            let fortune = json_body(&with_accept(accept));
+            assert!(fortune["mechanism"].is_string());
+        }
+        
+        let response = with_accept("text/html;q=0.9, text/plain");
+        assert_eq!(response.header("content-type").and_then(|value| value.as_str()), Some("text/plain; charset=utf-8"));
+        let text = String::from_utf8(response.body().to_vec()).unwrap();
+        let fortune = json_body(&with_accept("application/json"));
+        let haiku: Vec<&str> = fortune["haiku"].as_array().unwrap().iter().map(|line| line.as_str().unwrap()).collect();
+        assert_eq!(text, format!("{}\n", haiku.join("\n")));
+    }
+    
+    
+    #[test]
+    fn escpos_job_starts_with_init_and_ends_with_cut() {
+        let job = render_escpos(&sample_fortune());
+        assert!(job.starts_with(&[0x1b, 0x40]));
+        assert!(job.ends_with(&[0x1d, 0x56, 0x01]));
+        // Em dashes are mapped, so the job stays within the printer's ASCII code page
+        assert!(job.windows(b"waits-".len()).any(|window| window == b"waits-"));
+        assert!(job.iter().all(u8::is_ascii));
+    }
+    
+    
+    #[test]
+    fn svg_card_is_well_formed_and_escaped() {
+        let mut fortune = sample_fortune();
+        fortune.haiku[1] = r#"Tags like <b> & "quotes">"#.to_string();
+        let svg = render_svg(&fortune);
+        
+        let document = roxmltree::Document::parse(&svg).unwrap();
+        assert_eq!(document.root_element().tag_name().name(), "svg");
+        let texts: Vec<&str> = document.descendants().filter(|node| node.has_tag_name("text")).filter_map(|node| node.text()).collect();
+        assert!(texts.contains(&r#"Tags like <b> & "quotes">"#));
+        assert!(texts.contains(&"Quantum paths unfold,"));
+    }
+    
+    
+    #[test]
+    fn count_bundles_distinct_fortunes_within_bounds() {
+        let fetch = |query: &str| json_body(&route_request(&get(&format!("/api/oracle/fortune?seed=42&{}", query))));
+        
+        assert!(fetch("count=1").is_object());
+        assert!(fetch("count=0").is_object());
+        
+        let bundle = fetch("count=3");
+        let haiku: HashSet<String> = bundle.as_array().unwrap().iter().map(|fortune| fortune["haiku"].to_string()).collect();
+        assert_eq!(haiku.len(), 3);
+        
+        assert_eq!(fetch("count=99").as_array().unwrap().len(), MAX_BUNDLE_SIZE as usize);
+    }
+    
+    
+    #[test]
+    fn theme_filters_templates_or_reports_no_match() {
+        set_variable("rare_fortune_chance", "0");
+        for theme in ["desert", "math", "emergence", "paradox"] {
+            let fortune = json_body(&route_request(&get(&format!("/api/oracle/fortune?theme={}", theme))));
+            assert_eq!(fortune["theme"], theme);
+            assert_eq!(fortune["theme_matched"], true);
+        }
+        
+        let fortune = json_body(&route_request(&get("/api/oracle/fortune?theme=jungle")));
+        assert_eq!(fortune["theme_matched"], false);
+        assert_eq!(fortune["haiku"].as_array().unwrap().len(), 3);
+        
+        // Unthemed requests don't report a match either way
+        assert!(json_body(&route_request(&get("/api/oracle/fortune")))["theme_matched"].is_null());
+    }
+    
+    
+    #[test]
+    fn state_is_parsed_once_per_request_until_its_mtime_changes() {
+        let path = std::env::temp_dir().join(format!("zeldar-memo-{}.json", std::process::id()));
+        let written = SystemTime::now() - Duration::from_secs(60);
+        let write_state = |phi: f64, modified: SystemTime| {
+            std::fs::write(&path, format!(r#"{{"information-dynamics_phi": {}}}"#, phi)).unwrap();
+            std::fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+        };
+        let phi = || read_loop_state_at(&path, "").unwrap().unwrap().phi;
+        
+        write_state(1.0, written);
+        assert_eq!(phi(), Some(1.0));
+        // Same mtime: the memo answers without re-parsing the new content
+        write_state(2.0, written);
+        assert_eq!(phi(), Some(1.0));
+        // A new mtime invalidates it
+        write_state(3.0, written + Duration::from_secs(1));
+        assert_eq!(phi(), Some(3.0));
+        // As does a reload
+        write_state(4.0, written + Duration::from_secs(1));
+        invalidate_loop_state_cache();
+        assert_eq!(phi(), Some(4.0));
+        // And so does the next request
+        write_state(5.0, written + Duration::from_secs(1));
+        set_current_request_id("next-request");
+        assert_eq!(phi(), Some(5.0));
+        
+        std::fs::remove_file(&path).unwrap();
+        assert!(read_loop_state_at(&path, "").unwrap().is_none());
+    }
+    
+    
+    #[test]
+    fn metrics_carry_the_raw_state_values() {
+        let dir = fixture_topos("raw", r#"{"information-dynamics_phi": 3.252, "quantum_entropy": 0.926, "loop_iteration": 42}"#);
+        let metrics = json_body(&route_request(&get("/api/information-dynamics/metrics")));
+        assert_eq!(metrics["phi"], 3.252);
+        assert_eq!(metrics["quantum_entropy"], 0.926);
+        assert_eq!(metrics["loop_iteration"], 42);
+        std::fs::remove_dir_all(dir).unwrap();
+        
+        // The simulation has no raw values, and omits the keys entirely
+        let metrics = json_body(&route_request(&get("/api/information-dynamics/metrics")));
+        for key in ["phi", "quantum_entropy", "loop_iteration"] {
+            assert!(metrics.get(key).is_none(), "{}", key);
+        }
+    }
+    
+    
+    #[test]
+    fn fortune_json_keeps_the_hyphenated_metrics_key() {
+        let json = serde_json::to_value(sample_fortune()).unwrap();
+        assert!(json.get("information_dynamics").is_none());
+        assert_eq!(json["information-dynamics"]["semantic_closure"], 0.9252);
+        
+        let parsed: FortuneResponse = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.information_dynamics.phi, Some(3.252));
+    }
+    
+    
+    #[test]
+    fn interface_shows_the_live_closure() {
+        let dir = fixture_topos("interface", r#"{"information-dynamics_phi": 3.252, "quantum_entropy": 0.926, "loop_iteration": 9}"#);
+        let response = route_request(&get("/"));
+        let html = String::from_utf8(response.body().to_vec()).unwrap();
+        assert!(html.contains(r#"<span id="semantic-closure">92.5%</span>"#));
+        assert!(!html.contains("88.5%"));
+        std::fs::remove_dir_all(dir).unwrap();
+        
+        assert_eq!(fmt_pct(f64::NAN), METRIC_PLACEHOLDER);
+        assert_eq!(fmt_coef(f64::INFINITY), METRIC_PLACEHOLDER);
+    }
+    
+    /// Drive the metrics stream into a channel, pausing through `pause` between ticks
+    fn collect_stream_events(last_event_id: u64, started: Instant, pause: impl Fn()) -> Vec<String> {
+        let (sender, receiver) = futures::channel::mpsc::unbounded();
+        futures::executor::block_on(write_metrics_events(sender, last_event_id, started, || {
+            pause();
+            async {}
+        }));
+        futures::executor::block_on_stream(receiver).map(|event| String::from_utf8(event).unwrap()).collect()
+    }
+    
+    #[test]
+    fn metrics_stream_numbers_events_until_closed() {
+        let ticks = std::cell::Cell::new(0);
+        let events = collect_stream_events(41, Instant::now(), || {
+            ticks.set(ticks.get() + 1);
+            if ticks.get() == 2 {
+                set_variable("maintenance_mode", "true");
+            }
+        });
+        
+        assert_eq!(events.len(), 3);
+        assert!(events[0].starts_with("retry: 2000\n\nid: 42\nevent: metrics\ndata: {"));
+        assert!(events[1].starts_with("id: 43\nevent: metrics\ndata: {"));
+        for event in &events[..2] {
+            let data = event.lines().find_map(|line| line.strip_prefix("data: ")).unwrap();
+            let metrics: InformationForceMetrics = serde_json::from_str(data).unwrap();
+            assert_eq!(metrics.source, Some(MetricSource::Simulation));
+        }
+        assert_eq!(events[2], "event: close\ndata: maintenance\n\n");
+    }
+    
+    #[test]
+    fn heartbeats_decide_component_liveness() {
+        let dir = fixture_topos("heartbeats", "{}");
+        let heartbeats = dir.join("heartbeats");
+        std::fs::create_dir_all(&heartbeats).unwrap();
+        std::fs::write(heartbeats.join("oracle.json"), "{}").unwrap();
+        let stale = std::fs::File::create(heartbeats.join("print.json")).unwrap();
+        stale
+            .set_modified(std::time::SystemTime::now() - Duration::from_secs(HEARTBEAT_FRESHNESS_SECS * 2))
+            .unwrap();
+        std::fs::write(dir.join("button_quick_phrase_trigger.py"), "").unwrap();
+        
+        let status = assess_tri_loop_system(&MemoryStateSource::missing());
+        assert!(status.mcp_active, "a fresh heartbeat is active");
+        assert!(!status.gemini_connected, "a stale heartbeat is inactive");
+        assert!(status.codex_generating, "without a heartbeat the script's existence decides");
+        assert_eq!(status.detection_method, "mixed");
+    }
+    
+    #[test]
+    fn mechanisms_endpoint_lists_what_the_selector_produces() {
+        let listed: Vec<String> = serde_json::from_value(json_body(&route_request(&get("/api/oracle/mechanisms")))["mechanisms"].take()).unwrap();
+        
+        let mut selected: HashSet<String> = (0..500).map(|seed| select_generation_mechanism(&sample_metrics(), Some(seed))).collect();
+        selected.insert(select_generation_mechanism(&sample_metrics(), None));
+        
+        assert_eq!(listed.len(), selected.len(), "no duplicates are listed");
+        assert_eq!(listed.into_iter().collect::<HashSet<_>>(), selected);
+    }
+    
+    #[test]
+    fn daily_fortune_changes_only_with_the_date() {
+        set_variable("rare_fortune_chance", "0");
+        let daily = |days| json_body(&get_daily_fortune(HashMap::new(), None, days).unwrap());
+        
+        // 2024-02-29
+        let today = daily(19_782);
+        assert_eq!(today["date"], "2024-02-29");
+        assert_eq!(daily(19_782)["haiku"], today["haiku"]);
+        
+        let tomorrow = daily(19_783);
+        assert_eq!(tomorrow["date"], "2024-03-01");
+        assert_ne!(tomorrow["haiku"], today["haiku"]);
+        
+        // Including across the deck's restarts
+        let month: Vec<_> = (19_782..19_812).map(|days| daily(days)["haiku"].take()).collect();
+        assert!(month.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+    
+    const CONTRIBUTED_HAIKU: &str = r#"{"lines":["Quantum paths unfold,","Mathematical grace waits—","Information near"],"author":"Ada"}"#;
+    
+    #[test]
+    fn contributions_queue_for_moderation() {
+        let response = route_request(&post("/api/oracle/contribute", CONTRIBUTED_HAIKU));
+        assert_eq!(*response.status(), 201);
+        let id = json_body(&response)["id"].as_str().unwrap().to_string();
+        
+        let store = Store::open_default().unwrap();
+        let stored: Contribution =
+            serde_json::from_slice(&store.get(&format!("{}{}", PENDING_CONTRIBUTION_PREFIX, id)).unwrap().unwrap()).unwrap();
+        assert_eq!(stored.lines[1], "Mathematical grace waits—");
+        assert_eq!(stored.author.as_deref(), Some("Ada"));
+        assert!(load_approved_contributions().is_empty(), "nothing is selectable before moderation");
+        
+        let two_lines = route_request(&post("/api/oracle/contribute", r#"{"lines":["Quantum paths unfold,","Information near"]}"#));
+        assert_eq!(*two_lines.status(), 400);
+    }
+    
+    /// A moderation request carrying `key` as its bearer token, if any
+    fn moderation(method: Method, uri: &str, body: &str, key: Option<&str>) -> Response {
+        let mut request = Request::builder();
+        request.method(method).uri(uri).body(body.as_bytes().to_vec());
+        if let Some(key) = key {
+            request.header("authorization", format!("Bearer {}", key));
+        }
+        route_request(&request.build())
+    }
+    
+    #[test]
+    fn moderators_approve_and_reject_contributions() {
+        set_variable("moderation_api_key", "sesame");
+        let submit = |author: &str| {
+            let body = CONTRIBUTED_HAIKU.replace("Ada", author);
+            json_body(&route_request(&post("/api/oracle/contribute", &body)))["id"].as_str().unwrap().to_string()
+        };
+        let (kept, dropped) = (submit("Ada"), submit("Grace"));
+        
+        let pending = json_body(&moderation(Method::Get, "/api/oracle/moderation/pending", "", Some("sesame")));
+        assert_eq!(pending["pending"].as_array().unwrap().len(), 2);
+        
+        let approve = r#"{"action":"approve"}"#;
+        let response = moderation(Method::Post, &format!("/api/oracle/moderation/{}", kept), approve, Some("sesame"));
+        assert_eq!(json_body(&response)["status"], "approved");
+        let response = moderation(Method::Post, &format!("/api/oracle/moderation/{}", dropped), r#"{"action":"reject"}"#, Some("sesame"));
+        assert_eq!(json_body(&response)["status"], "rejected");
+        
+        assert_eq!(load_approved_contributions().len(), 1, "only the approved haiku joins the pool");
+        let pending = json_body(&moderation(Method::Get, "/api/oracle/moderation/pending", "", Some("sesame")));
+        assert!(pending["pending"].as_array().unwrap().is_empty());
+        
+        let unknown = moderation(Method::Post, "/api/oracle/moderation/nonexistent", approve, Some("sesame"));
+        assert_eq!(*unknown.status(), 404);
+    }
+    
+    #[test]
+    fn moderation_requires_the_api_key() {
+        set_variable("moderation_api_key", "sesame");
+        for key in [None, Some("open")] {
+            assert_eq!(*moderation(Method::Get, "/api/oracle/moderation/pending", "", key).status(), 401);
+            assert_eq!(*moderation(Method::Post, "/api/oracle/moderation/any", r#"{"action":"approve"}"#, key).status(), 401);
+        }
+    }
+    
+    #[test]
+    fn metrics_history_keeps_only_its_capacity() {
+        set_variable("metrics_history_size", "3");
+        for step in 0..5 {
+            record_metrics_sample(&InformationForceMetrics { semantic_closure: step as f64 / 10.0, ..sample_metrics() });
+        }
+        
+        let history = json_body(&route_request(&get("/api/information-dynamics/history")));
+        let closures: Vec<f64> = history
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|sample| sample["metrics"]["semantic_closure"].as_f64().unwrap())
+            .collect();
+        assert_eq!(closures, [0.4, 0.3, 0.2], "the newest three, newest first");
+        
+        let limited = json_body(&route_request(&get("/api/information-dynamics/history?limit=1")));
+        assert_eq!(limited.as_array().unwrap().len(), 1);
+    }
+    
+    #[test]
+    fn smoothing_applies_an_ema_over_the_history() {
+        let history: VecDeque<MetricsSample> = [(0.2, 0.0), (0.6, 1.0), (1.0, 0.0)]
+            .into_iter()
+            .map(|(closure, correlation)| MetricsSample {
+                timestamp: 0,
+                metrics: InformationForceMetrics { semantic_closure: closure, correlation_strength: correlation, ..sample_metrics() },
+            })
+            .collect();
+        let current = history.back().unwrap().metrics.clone();
+        
+        // 0.2 -> 0.5 * 0.6 + 0.5 * 0.2 = 0.4 -> 0.5 * 1.0 + 0.5 * 0.4 = 0.7
+        let smoothed = smooth_metrics(current.clone(), &history, 0.5);
+        assert!((smoothed.semantic_closure - 0.7).abs() < 1e-12);
+        assert!((smoothed.correlation_strength - 0.25).abs() < 1e-12);
+        assert_eq!(smoothed.raw_semantic_closure, Some(1.0));
+        assert_eq!(smoothed.raw_correlation_strength, Some(0.0));
+        
+        // Alpha above 1 clamps to 1, which is no smoothing
+        assert_eq!(smooth_metrics(current.clone(), &history, 5.0).semantic_closure, 1.0);
+        
+        let unchanged = smooth_metrics(current.clone(), &VecDeque::new(), 0.5);
+        assert_eq!(unchanged.semantic_closure, current.semantic_closure);
+        assert_eq!(unchanged.raw_semantic_closure, None);
+    }
+    
+    #[test]
+    fn sanitize_clamps_and_replaces_non_finite_metrics() {
+        let dirty = InformationForceMetrics {
+            semantic_closure: f64::NAN,
+            hofstadter_coefficient: f64::INFINITY,
+            spectral_gap: -2.0,
+            correlation_strength: 1.7,
+            ..sample_metrics()
+        };
+        let clean = sanitize(dirty);
+        assert_eq!(clean.semantic_closure, 0.0);
+        assert_eq!(clean.hofstadter_coefficient, 1.0);
+        assert_eq!(clean.spectral_gap, 0.0);
+        assert_eq!(clean.correlation_strength, 1.0);
+        
+        let negative = sanitize(InformationForceMetrics { semantic_closure: -0.3, correlation_strength: -1.0, ..sample_metrics() });
+        assert_eq!((negative.semantic_closure, negative.correlation_strength), (0.0, 0.0));
+        
+        let in_range = sample_metrics();
+        assert_eq!(sanitize(in_range.clone()).semantic_closure, in_range.semantic_closure);
+    }
+    
+    #[test]
+    fn correlation_reason_explains_a_missing_or_stale_state() {
+        let dir = fixture_topos("correlation", r#"{"information-dynamics_phi": 3.252, "quantum_entropy": 0.5, "loop_iteration": 7}"#);
+        let state = dir.join("current_loop_state.json");
+        let status = assess_tri_loop_system(&FileStateSource);
+        assert!(status.correlation_detected);
+        assert_eq!(status.correlation_reason.as_deref(), Some("ok"));
+        
+        std::fs::File::options()
+            .write(true)
+            .open(&state)
+            .unwrap()
+            .set_modified(std::time::SystemTime::now() - Duration::from_secs(STATE_FRESHNESS_SECS + 60))
+            .unwrap();
+        invalidate_loop_state_cache();
+        let status = assess_tri_loop_system(&FileStateSource);
+        assert!(!status.correlation_detected);
+        assert!(status.correlation_reason.as_deref().is_some_and(|reason| reason.starts_with("state_stale_") && reason.ends_with('s')));
+        
+        std::fs::remove_file(&state).unwrap();
+        invalidate_loop_state_cache();
+        let status = assess_tri_loop_system(&FileStateSource);
+        assert!(!status.correlation_detected);
+        assert_eq!(status.correlation_reason.as_deref(), Some("state_file_missing"));
+    }
+    
+    #[test]
+    fn openapi_documents_every_route() {
+        let response = route_request(&get("/api/openapi.json"));
+        let document = json_body(&response);
+        let paths = document["paths"].as_object().unwrap();
+        
+        for (path, _) in STATIC_ROUTES {
+            assert!(paths.contains_key(*path), "{} is undocumented", path);
+        }
+        
+        // Every documented path is served, and the templated ones cover each dynamic route
+        let concrete: Vec<(&String, String)> = paths
+            .keys()
+            .map(|path| (path, path.replace("{id}", "abc").replace("{slug}", "abc").replace("{code}", "abc123")))
+            .collect();
+        let mut dynamic = HashSet::new();
+        for (path, concrete) in &concrete {
+            let route = Route::from_path(concrete);
+            assert_ne!(route, Route::Unknown, "{} is documented but not routed", path);
+            if path.contains('{') {
+                dynamic.insert(std::mem::discriminant(&route));
+            }
+        }
+        let expected = [
+            Route::StoredFortune(""),
+            Route::FortuneQr(""),
+            Route::Mechanism(""),
+            Route::Moderate(""),
+            Route::ShortLink(""),
+            Route::FortuneView(""),
+        ];
+        assert_eq!(dynamic, expected.iter().map(std::mem::discriminant).collect());
+    }
+    
+    #[test]
+    fn markdown_quotes_the_haiku_and_escapes_it() {
+        let markdown = render_markdown(&sample_fortune());
+        assert!(markdown.starts_with("> Quantum paths unfold,  \n> Mathematical grace waits—  \n> Information near  \n"));
+        assert!(markdown.contains(&format!("*{}*", escape_markdown(MECHANISMS[0]))));
+        assert!(markdown.contains("| Semantic closure | 92.5% |"));
+        
+        let mut fortune = sample_fortune();
+        fortune.haiku[0] = "*Loops* [link](x)".to_string();
+        assert!(render_markdown(&fortune).starts_with(r"> \*Loops\* \[link\]\(x\)"));
+        
+        set_variable("rare_fortune_chance", "0");
+        let response = route_request(&get("/api/oracle/fortune?format=markdown"));
+        assert_eq!(response.header("content-type").and_then(|value| value.as_str()), Some("text/markdown; charset=utf-8"));
+        assert!(String::from_utf8_lossy(response.body()).starts_with("> "));
+    }
+    
+    #[test]
+    fn lang_selects_the_template_table() {
+        set_variable("rare_fortune_chance", "0");
+        let lines_of = |lang: &str| -> Vec<Vec<String>> { template_pool(lang).iter().map(|template| template.lines.to_vec()).collect() };
+        let fortune = |query: &str| json_body(&route_request(&get(&format!("/api/oracle/fortune?seed=7&{}", query))));
+        
+        let spanish = fortune("lang=es");
+        assert_eq!(spanish["lang"], "es");
+        assert!(lines_of("es").contains(&serde_json::from_value(spanish["haiku"].clone()).unwrap()));
+        assert!(!lines_of("en").contains(&serde_json::from_value(spanish["haiku"].clone()).unwrap()));
+        
+        for query in ["lang=fr", "lang=", ""] {
+            let fallback = fortune(query);
+            assert_eq!(fallback["lang"], "en", "{:?} falls back to English", query);
+            assert!(lines_of("en").contains(&serde_json::from_value(fallback["haiku"].clone()).unwrap()));
+        }
+    }
+    
+    #[test]
+    fn client_ip_prefers_the_first_forwarded_hop() {
+        let ip = |headers: &[(&str, &str)]| {
+            let mut request = Request::builder();
+            request.method(Method::Get).uri("/api/health");
+            for (name, value) in headers {
+                request.header(*name, *value);
+            }
+            client_ip(&request.build())
+        };
+        let peer = ("spin-client-addr", "10.0.0.2:51234");
+        
+        assert_eq!(ip(&[("x-forwarded-for", "203.0.113.7")]), "203.0.113.7".parse().ok());
+        assert_eq!(ip(&[("x-forwarded-for", " 203.0.113.7 , 10.0.0.1,10.0.0.2"), peer]), "203.0.113.7".parse().ok());
+        assert_eq!(ip(&[("x-forwarded-for", "[2001:db8::1]:443")]), "2001:db8::1".parse().ok());
+        assert_eq!(ip(&[("x-forwarded-for", "not-an-ip, 203.0.113.7"), peer]), "10.0.0.2".parse().ok());
+        assert_eq!(ip(&[peer]), "10.0.0.2".parse().ok());
+        assert_eq!(ip(&[("x-forwarded-for", "")]), None);
+    }
+    
+    #[test]
+    fn request_id_is_reused_or_generated() {
+        let with_id = |id: &str| {
+            let mut request = Request::builder();
+            request.method(Method::Get).uri("/api/health").header("x-request-id", id);
+            request_id(&request.build())
+        };
+        
+        assert_eq!(with_id(" booth-7f3a "), "booth-7f3a");
+        
+        let generated = request_id(&get("/api/health"));
+        assert_eq!(generated.len(), 36);
+        assert!(generated.chars().all(|c| c == '-' || c.is_ascii_hexdigit()));
+        assert_ne!(request_id(&get("/api/health")), generated, "each request gets its own id");
+        
+        // Ids that can't be echoed safely in a header or log line are replaced
+        for unusable in ["two words", &"x".repeat(MAX_REQUEST_ID_LEN + 1)] {
+            assert_ne!(with_id(unusable), unusable);
+        }
+    }
+    
+    #[test]
+    fn closure_bands_pick_the_template_theme() {
+        set_variable("rare_fortune_chance", "0");
+        for (closure, theme) in [(1.0, "emergence"), (0.95, "emergence"), (0.93, "math"), (0.90, "math"), (0.87, "desert"), (0.5, "paradox"), (f64::NAN, "paradox")] {
+            assert_eq!(closure_band_theme(closure), theme, "closure {}", closure);
+            
+            let metrics = InformationForceMetrics { semantic_closure: closure, threshold_exceeded: true, ..sample_metrics() };
+            let params = GenerationParams { live: false, ..GenerationParams::default() };
+            let fortune = &build_fortunes(&params, metrics, &MemoryStateSource::missing())[0];
+            assert_eq!(fortune.theme.as_deref(), Some(theme), "closure {}", closure);
+        }
+    }
+    
+    #[test]
+    fn posted_metrics_drive_the_fortune() {
+        set_variable("rare_fortune_chance", "0");
+        let body = |closure: f64| {
+            serde_json::to_string(&InformationForceMetrics { semantic_closure: closure, threshold_exceeded: true, ..sample_metrics() }).unwrap()
+        };
+        
+        let fortune = json_body(&route_request(&post("/api/oracle/fortune?source=body", &body(0.99))));
+        assert_eq!(fortune["theme"], "emergence");
+        assert_eq!(fortune["information-dynamics"]["semantic_closure"], 0.99);
+        assert!(fortune["information-dynamics"]["source"].is_null(), "callers can't claim a source");
+        
+        let clamped = json_body(&route_request(&post("/api/oracle/fortune?source=body", &body(7.0))));
+        assert_eq!(clamped["information-dynamics"]["semantic_closure"], 1.0);
+        
+        assert_eq!(*route_request(&post("/api/oracle/fortune", &body(0.99))).status(), 400);
+    }
+    
+    #[test]
+    fn favicon_and_unknown_pages() {
+        let favicon = route_request(&get("/favicon.ico"));
+        assert_eq!(*favicon.status(), 200);
+        assert_eq!(favicon.header("content-type").and_then(|value| value.as_str()), Some("image/svg+xml"));
+        assert!(favicon.body().starts_with(b"<svg"));
+        
+        let missing = route_request(&get("/nonexistent"));
+        assert_eq!(*missing.status(), 404);
+        assert!(missing.header("content-type").and_then(|value| value.as_str()).is_some_and(|value| value.starts_with("text/html")));
+        
+        assert_eq!(*route_request(&get("/api/nonexistent")).status(), 404);
+        assert_eq!(*route_request(&get("/")).status(), 200);
+    }
+    
+    #[test]
+    fn history_ndjson_is_one_object_per_line() {
+        for step in 0..4 {
+            record_metrics_sample(&InformationForceMetrics { semantic_closure: step as f64 / 10.0, ..sample_metrics() });
+        }
+        
+        let response = route_request(&get("/api/information-dynamics/history?format=ndjson"));
+        assert_eq!(response.header("content-type").and_then(|value| value.as_str()), Some("application/x-ndjson"));
+        let body = std::str::from_utf8(response.body()).unwrap();
+        assert!(body.ends_with("}\n") && !body.ends_with("\n\n"));
+        
+        let samples: Vec<MetricsSample> = body.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(samples.len(), 4);
+        assert_eq!(samples[0].metrics.semantic_closure, 0.3, "newest first, as in JSON");
+    }
+    
+    #[test]
+    fn spectral_gap_shares_one_scale_across_sources() {
+        let cases = [
+            (MetricSource::StateFile, 5.0, 5.0),
+            (MetricSource::StateFile, 12.0, 10.0),
+            (MetricSource::StateFile, -1.0, 0.0),
+            (MetricSource::Query, 3.5, 3.5),
+            (MetricSource::Simulation, 5.26, 0.0),
+            (MetricSource::Simulation, 6.26, 5.0),
+            (MetricSource::Simulation, 7.26, 10.0),
+        ];
+        for (source, raw, normalized) in cases {
+            assert!((normalize_spectral_gap(raw, source) - normalized).abs() < 1e-9, "{:?} {}", source, raw);
+        }
+    }
+    
+    #[test]
+    fn state_missing_keys_degrades_the_reading() {
+        fixture_topos("incomplete", r#"{"information-dynamics_phi": 3.252, "loop_iteration": 7}"#);
+        
+        let metrics = json_body(&route_request(&get("/api/information-dynamics/metrics")));
+        assert_eq!(metrics["source"], "state_file");
+        assert_eq!(metrics["threshold_exceeded"], false);
+        
+        let status = assess_tri_loop_system(&FileStateSource);
+        assert!(!status.correlation_detected);
+        assert_eq!(status.correlation_reason.as_deref(), Some("state_incomplete"));
+    }
+    
+    #[test]
+    fn sequential_visitors_get_no_repeat_within_the_window() {
+        set_variable("rare_fortune_chance", "0");
+        set_variable("anti_repetition_window", "3");
+        
+        let haiku: Vec<serde_json::Value> = (0..8)
+            .map(|_| json_body(&route_request(&get("/api/oracle/fortune")))["haiku"].take())
+            .collect();
+        for (i, current) in haiku.iter().enumerate() {
+            assert!(!haiku[i.saturating_sub(3)..i].contains(current), "request {} repeats {}", i, current);
+        }
+        
+        // Seeded requests are exempt and stay reproducible
+        let seeded = || json_body(&route_request(&get("/api/oracle/fortune?seed=11")))["haiku"].take();
+        assert_eq!(seeded(), seeded());
+    }
+    
+    /// Check `value` against the subset of JSON Schema that `fortune_schemas` uses,
+    /// resolving `$ref`s in `defs`
+    fn assert_matches_schema(value: &serde_json::Value, schema: &serde_json::Value, defs: &serde_json::Value, at: &str) {
+        if let Some(reference) = schema["$ref"].as_str() {
+            let name = reference.rsplit('/').next().unwrap();
+            return assert_matches_schema(value, &defs[name], defs, at);
+        }
+        
+        let type_ok = match schema["type"].as_str() {
+            Some("object") => value.is_object(),
+            Some("array") => value.is_array(),
+            Some("string") => value.is_string(),
+            Some("integer") => value.is_u64() || value.is_i64(),
+            Some("number") => value.is_number(),
+            Some("boolean") => value.is_boolean(),
+            _ => true,
+        };
+        assert!(type_ok, "{} should be {}: {}", at, schema["type"], value);
+        
+        if let Some(allowed) = schema["enum"].as_array() {
+            assert!(allowed.contains(value), "{} = {} is outside {:?}", at, value, allowed);
+        }
+        if let Some(number) = value.as_f64() {
+            assert!(schema["minimum"].as_f64().map_or(true, |min| number >= min), "{} below minimum", at);
+            assert!(schema["maximum"].as_f64().map_or(true, |max| number <= max), "{} above maximum", at);
+        }
+        if let Some(items) = value.as_array() {
+            assert!(schema["minItems"].as_u64().map_or(true, |min| items.len() as u64 >= min), "{} too short", at);
+            assert!(schema["maxItems"].as_u64().map_or(true, |max| items.len() as u64 <= max), "{} too long", at);
+            for (i, item) in items.iter().enumerate() {
+                assert_matches_schema(item, &schema["items"], defs, &format!("{}[{}]", at, i));
+            }
+        }
+        if let Some(object) = value.as_object() {
+            for key in schema["required"].as_array().into_iter().flatten() {
+                assert!(object.contains_key(key.as_str().unwrap()), "{} lacks required {}", at, key);
+            }
+            for (key, field) in object {
+                let declared = &schema["properties"][key];
+                assert!(!declared.is_null(), "{}.{} is undeclared", at, key);
+                assert_matches_schema(field, declared, defs, &format!("{}.{}", at, key));
+            }
+        }
+    }
+    
+    #[test]
+    fn generated_fortunes_match_the_published_schema() {
+        set_variable("rare_fortune_chance", "0");
+        let schema = json_body(&route_request(&get("/api/schema/fortune")));
+        assert_eq!(schema["$defs"]["FortuneResponse"]["required"].as_array().unwrap().iter().filter(|key| *key == "information-dynamics").count(), 1);
+        
+        for query in ["", "?validate=true&explain=true&explain_mechanism=true&time_format=iso&theme=math&nonce=abc123"] {
+            let fortune = json_body(&route_request(&get(&format!("/api/oracle/fortune{}", query))));
+            assert_matches_schema(&fortune, &schema, &schema["$defs"], "fortune");
+        }
+        let fortune = serde_json::to_value(sample_fortune()).unwrap();
+        assert_matches_schema(&fortune, &schema, &schema["$defs"], "sample");
+    }
+    
+    #[test]
+    fn head_mirrors_get_without_a_body() {
+        // A fixed state file keeps the two readings byte-identical
+        fixture_topos("head", r#"{"information-dynamics_phi": 3.252, "quantum_entropy": 0.5, "loop_iteration": 7}"#);
+        let head_request = Request::builder().method(Method::Head).uri("/api/information-dynamics/metrics").build();
+        
+        let get_response = route_request(&get("/api/information-dynamics/metrics"));
+        let head_response = route_request(&head_request);
+        assert_eq!(*head_response.status(), 200);
+        assert!(head_response.body().is_empty());
+        assert_eq!(
+            head_response.header("content-length").and_then(|value| value.as_str()),
+            Some(get_response.body().len().to_string().as_str())
+        );
+        assert_eq!(head_response.header("content-type").and_then(|value| value.as_str()), Some("application/json"));
+        
+        assert_eq!(*route_request(&Request::builder().method(Method::Head).uri("/api/nonexistent").build()).status(), 404);
+    }
+    
+    #[test]
+    fn consciousness_threshold_applies_to_both_sources() {
+        let state = MemoryStateSource::from_json(r#"{"information-dynamics_phi": 3.252, "quantum_entropy": 0.5, "loop_iteration": 7}"#);
+        let exceeded = |threshold: &str, source: &dyn StateSource, mode| {
+            set_variable("consciousness_threshold", threshold);
+            read_information_dynamics_metrics(source, mode).unwrap().threshold_exceeded
+        };
+        
+        // Φ 3.252 is closure 0.9252
+        assert!(exceeded("0.925", &state, MetricsMode::File));
+        assert!(!exceeded("0.926", &state, MetricsMode::File));
+        
+        // The simulation's closure stays within [0.885, 0.985]
+        assert!(exceeded("0.884", &MemoryStateSource::missing(), MetricsMode::Simulation));
+        assert!(!exceeded("0.986", &MemoryStateSource::missing(), MetricsMode::Simulation));
+    }
+    
+    #[test]
+    fn stats_count_each_fortune_served() {
+        set_variable("rare_fortune_chance", "0");
+        let stats = || json_body(&route_request(&get("/api/oracle/stats")));
+        let before = stats()["total"].as_i64().unwrap();
+        
+        json_body(&route_request(&get("/api/oracle/fortune?count=2")));
+        json_body(&route_request(&get("/api/oracle/fortune")));
+        
+        let after = stats();
+        assert_eq!(after["total"].as_i64().unwrap(), before + 3);
+        let sum = |breakdown: &serde_json::Value| breakdown.as_object().unwrap().values().map(|count| count.as_i64().unwrap()).sum::<i64>();
+        assert_eq!(sum(&after["mechanisms"]), before + 3);
+        assert_eq!(sum(&after["themes"]), before + 3);
+    }
+    
+    #[test]
+    fn web_interface_can_be_swapped_for_a_json_index() {
+        let page = route_request(&get("/"));
+        assert!(page.header("content-type").and_then(|value| value.as_str()).is_some_and(|value| value.starts_with("text/html")));
+        
+        set_variable("enable_web_interface", "false");
+        let mut index = json_body(&route_request(&get("/")));
+        assert_eq!(index["service"], "zeldar-oracle");
+        let endpoints: Vec<String> = serde_json::from_value(index["endpoints"].take()).unwrap();
+        assert!(endpoints.contains(&"/api/oracle/fortune".to_string()));
+        for endpoint in &endpoints {
+            assert!(STATIC_ROUTES.iter().any(|(path, _)| path == endpoint), "{} isn't routed", endpoint);
+            assert_ne!(Route::from_path(endpoint), Route::Interface);
+        }
+    }
+    
+    #[test]
+    fn burning_man_mode_swaps_the_desert_templates() {
+        let status = || json_body(&route_request(&get("/api/information-dynamics/status")));
+        let festival = compiled_templates("en");
+        assert_eq!(status()["burning_man_mode"], true);
+        
+        set_variable("burning_man_mode", "false");
+        let neutral = compiled_templates("en");
+        assert_eq!(status()["burning_man_mode"], false);
+        
+        for (festival, neutral) in festival.iter().zip(&neutral) {
+            assert_eq!(festival.theme, neutral.theme);
+            if neutral.theme == "desert" {
+                assert_ne!(festival.lines, neutral.lines);
+                assert!(neutral.lines.iter().all(|line| !line.to_lowercase().contains("desert") && !line.to_lowercase().contains("dune")));
+            } else {
+                assert_eq!(festival.lines, neutral.lines);
+            }
+        }
+    }
+    
+    #[test]
+    fn reload_returns_the_edited_state() {
+        set_variable("moderation_api_key", "sesame");
+        let dir = fixture_topos("reload", r#"{"information-dynamics_phi": 3.252, "quantum_entropy": 0.5, "loop_iteration": 7}"#);
+        let reload = |key| moderation(Method::Post, "/api/information-dynamics/reload", "", key);
+        assert_eq!(json_body(&route_request(&get("/api/information-dynamics/metrics")))["semantic_closure"], 0.9252);
+        
+        std::fs::write(dir.join("current_loop_state.json"), r#"{"information-dynamics_phi": 2.0, "quantum_entropy": 0.5, "loop_iteration": 8}"#).unwrap();
+        let reloaded = json_body(&reload(Some("sesame")));
+        assert_eq!(reloaded["semantic_closure"], 0.8);
+        assert_eq!(reloaded["loop_iteration"], 8);
+        
+        assert_eq!(*reload(None).status(), 401);
+        std::fs::remove_file(dir.join("current_loop_state.json")).unwrap();
+        let missing = reload(Some("sesame"));
+        assert_eq!(*missing.status(), 503);
+        let error = serde_json::from_slice::<serde_json::Value>(missing.body()).unwrap()["error"].take();
+        assert_eq!(error["code"], "unavailable");
+        assert_eq!(error["message"], "Oracle state file is missing");
+    }
+    
+    #[test]
+    fn reading_combines_every_section_from_one_parse() {
+        set_variable("rare_fortune_chance", "0");
+        fixture_topos("reading", r#"{"information-dynamics_phi": 3.252, "quantum_entropy": 0.5, "loop_iteration": 7}"#);
+        set_current_request_id("reading-request");
+        let parses_before = STATE_PARSES.with(std::cell::Cell::get);
+        
+        let reading = json_body(&route_request(&get("/api/oracle/reading")));
+        assert_eq!(STATE_PARSES.with(std::cell::Cell::get) - parses_before, 1);
+        
+        assert!(reading["fortune"]["haiku"].is_array());
+        assert_eq!(reading["information-dynamics"]["semantic_closure"], 0.9252);
+        assert_eq!(reading["fortune"]["information-dynamics"], reading["information-dynamics"]);
+        assert!(reading["tri_loop"]["correlation_detected"].is_boolean());
+        assert_eq!(reading["config"]["consciousness_threshold"], DEFAULT_CONSCIOUSNESS_THRESHOLD);
+    }
+    
+    #[test]
+    fn strange_loops_follow_the_hofstadter_coefficient() {
+        // Φ / 3 is the coefficient: 0.5, 1.0, 1.084, 1.2, 1.4, 2.0
+        for (phi, loops) in [(1.5, 3), (3.0, 4), (3.252, 4), (3.6, 5), (4.2, 6), (6.0, 7)] {
+            for iteration in [7, 8, 1_000] {
+                let state = format!(r#"{{"information-dynamics_phi": {}, "quantum_entropy": 0.5, "loop_iteration": {}}}"#, phi, iteration);
+                let metrics = read_information_dynamics_metrics(&MemoryStateSource::from_json(&state), MetricsMode::File).unwrap();
+                assert_eq!(metrics.strange_loops, loops, "phi {} at iteration {}", phi, iteration);
+            }
+        }
+        assert_eq!(strange_loops_for(f64::NAN), MIN_STRANGE_LOOPS);
+    }
+    
+    #[test]
+    fn timestamps_format_as_utc_dates() {
+        assert_eq!(format_timestamp_iso(0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(format_timestamp_iso(1_756_577_045_123), "2025-08-30T18:04:05.123Z");
+        
+        // Leap day, and the days either side of it
+        assert_eq!(format_timestamp_iso(1_709_164_800_000), "2024-02-29T00:00:00.000Z");
+        assert_eq!(format_timestamp_iso(1_709_164_800_000 - 1), "2024-02-28T23:59:59.999Z");
+        assert_eq!(format_date(1_709_164_800_000 / MILLIS_PER_DAY + 1), "2024-03-01");
+        // 2100 isn't a leap year
+        assert_eq!(format_date(47_540), "2100-02-28");
+        assert_eq!(format_date(47_541), "2100-03-01");
+        
+        // Year rollover
+        assert_eq!(format_timestamp_iso(1_735_689_600_000 - 1), "2024-12-31T23:59:59.999Z");
+        assert_eq!(format_timestamp_iso(1_735_689_600_000), "2025-01-01T00:00:00.000Z");
+        
+        set_variable("rare_fortune_chance", "0");
+        let fortune = json_body(&route_request(&get("/api/oracle/fortune?time_format=iso")));
+        assert_eq!(fortune["timestamp_iso"], format_timestamp_iso(fortune["timestamp"].as_u64().unwrap()));
+    }
+    
+    #[test]
+    fn require_threshold_skips_fortunes_below_it() {
+        set_variable("rare_fortune_chance", "0");
+        let fortune = |query: &str, closure: f64| {
+            let metrics = InformationForceMetrics { semantic_closure: closure, threshold_exceeded: closure > DEFAULT_CONSCIOUSNESS_THRESHOLD, ..sample_metrics() };
+            route_request(&post(&format!("/api/oracle/fortune?source=body{}", query), &serde_json::to_string(&metrics).unwrap()))
+        };
+        
+        let below = fortune("&require_threshold=true", 0.5);
+        assert_eq!(*below.status(), 204);
+        assert!(below.body().is_empty());
+        
+        let above = fortune("&require_threshold=true", 0.99);
+        assert!(json_body(&above)["haiku"].is_array());
+        
+        // Without the flag a standard haiku is still served
+        assert!(json_body(&fortune("", 0.5))["haiku"].is_array());
+    }
+    
+    #[test]
+    fn version_reports_the_crate_version() {
+        let version = json_body(&route_request(&get("/api/version")));
+        assert_eq!(version["version"], env!("CARGO_PKG_VERSION"));
+        assert!(!version["version"].as_str().unwrap().is_empty());
+        assert!(version["git_sha"].as_str().is_some_and(|sha| !sha.is_empty()));
+        assert!(version["build_time"].as_str().is_some_and(|time| time == "unknown" || time.ends_with('Z')));
+    }
+    
+    #[test]
+    fn acrostics_spell_the_word_or_are_refused() {
+        set_variable("rare_fortune_chance", "0");
+        let fortune = json_body(&route_request(&get("/api/oracle/fortune?acrostic=LOVE")));
+        assert_eq!(fortune["haiku"], serde_json::json!(["Light bends back on light", "Over and over it turns", "Voices weave as one"]));
+        assert_eq!(fortune["note"], "Acrostic word truncated to \"LOV\" - a haiku has three lines");
+        
+        // A short word leaves the remaining lines free
+        let short = build_acrostic("lo").unwrap();
+        assert!(short[0].starts_with('L') && short[1].starts_with('O') && !short[2].is_empty());
+        
+        // No line starts with X, and the one five-syllable A line can't open and close
+        assert_eq!(build_acrostic("AXE"), None);
+        assert_eq!(build_acrostic("ABA"), None);
+        assert_eq!(build_acrostic("A1"), None);
+        assert_eq!(*route_request(&get("/api/oracle/fortune?acrostic=XYZ")).status(), 400);
+    }
+    
+    #[test]
+    fn mechanism_follows_the_dominant_metric() {
+        let mechanism = |metrics: InformationForceMetrics| select_generation_mechanism(&metrics, None);
+        let quiet = InformationForceMetrics {
+            semantic_closure: 0.7,
+            strange_loops: MIN_STRANGE_LOOPS,
+            hofstadter_coefficient: 1.0,
+            spectral_gap: 0.0,
+            correlation_strength: 0.9,
+            ..sample_metrics()
+        };
+        
+        assert_eq!(mechanism(InformationForceMetrics { spectral_gap: 9.5, ..quiet.clone() }), "expander graph spectral gap resonance");
+        assert_eq!(mechanism(InformationForceMetrics { hofstadter_coefficient: 1.45, ..quiet.clone() }), "hofstadter coefficient recursive analysis");
+        assert_eq!(mechanism(InformationForceMetrics { strange_loops: 7, ..quiet.clone() }), "strange loop paradox resolution synthesis");
+        assert_eq!(mechanism(InformationForceMetrics { semantic_closure: 0.99, ..quiet.clone() }), "semantic closure boundary optimization");
+        // Ties go to the earlier mechanism
+        assert_eq!(
+            mechanism(InformationForceMetrics { semantic_closure: 1.0, correlation_strength: 1.0, ..quiet }),
+            "tri-loop correlation matrix convergence"
+        );
+    }
+    
+    #[test]
+    fn history_csv_has_a_header_and_a_row_per_sample() {
+        for step in 0..3 {
+            record_metrics_sample(&InformationForceMetrics { threshold_exceeded: step == 2, ..sample_metrics() });
+        }
+        
+        let response = route_request(&get("/api/information-dynamics/history?format=csv"));
+        assert_eq!(response.header("content-type").and_then(|value| value.as_str()), Some("text/csv"));
+        let csv = std::str::from_utf8(response.body()).unwrap();
+        let rows: Vec<Vec<&str>> = csv.split_terminator("\r\n").map(|row| row.split(',').collect()).collect();
+        
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[0], HISTORY_CSV_HEADER);
+        assert!(rows.iter().all(|row| row.len() == HISTORY_CSV_HEADER.len()));
+        assert_eq!(rows[1][1], "0.9252");
+        assert_eq!(rows.iter().skip(1).map(|row| row[6]).collect::<Vec<_>>(), ["1", "0", "0"], "newest first, booleans as 0/1");
+        
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+    }
+    
+    #[test]
+    fn explain_reports_the_source_and_template_index() {
+        set_variable("rare_fortune_chance", "0");
+        let metrics = InformationForceMetrics { semantic_closure: 0.99, threshold_exceeded: true, ..sample_metrics() };
+        let fortune = json_body(&route_request(&post("/api/oracle/fortune?source=body&explain=true", &serde_json::to_string(&metrics).unwrap())));
+        let explanation = &fortune["explanation"];
+        
+        let emergence = template_pool("en").iter().position(|template| template.theme == "emergence").unwrap();
+        assert_eq!(explanation["metrics_source"], "request_body");
+        assert_eq!(explanation["closure_band"], "emergence");
+        assert_eq!(explanation["haiku_source"], "template");
+        assert_eq!(explanation["template_index"], emergence);
+        assert_eq!(explanation["theme"], "emergence");
+        assert_eq!(explanation["seeded"], false);
+        assert_eq!(fortune["haiku"], serde_json::json!(template_pool("en")[emergence].lines));
+        
+        let plain = json_body(&route_request(&post("/api/oracle/fortune?source=body", &serde_json::to_string(&metrics).unwrap())));
+        assert!(plain.get("explanation").is_none(), "omitted by default");
+    }
+    
+    #[test]
+    fn aggregate_combines_state_files_and_skips_missing_ones() {
+        let dir = fixture_topos("aggregate", r#"{"information-dynamics_phi": 3.252, "quantum_entropy": 0.5, "loop_iteration": 7}"#);
+        std::fs::write(dir.join("b.json"), r#"{"information-dynamics_phi": 2.0, "quantum_entropy": 0.9, "loop_iteration": 3}"#).unwrap();
+        set_variable("state_files", "current_loop_state.json, b.json, missing.json");
+        
+        let body = json_body(&route_request(&get("/api/information-dynamics/aggregate")));
+        assert_eq!(body["oracles"].as_array().unwrap().len(), 2);
+        assert_eq!(body["skipped"], serde_json::json!([dir.join("missing.json").display().to_string()]));
+        
+        let aggregate = &body["aggregate"];
+        assert_eq!(aggregate["count"], 2);
+        assert!((aggregate["mean_semantic_closure"].as_f64().unwrap() - 0.8626).abs() < 1e-9);
+        assert!((aggregate["max_spectral_gap"].as_f64().unwrap() - 9.0).abs() < 1e-9);
+        assert_eq!(aggregate["any_threshold_exceeded"], true);
+    }
+    
+    #[test]
+    fn bodies_over_the_limit_get_413() {
+        set_variable("rare_fortune_chance", "0");
+        set_variable("max_body_bytes", "64");
+        let padded = |len: usize| format!("{{{}}}", " ".repeat(len - 2));
+        
+        assert_eq!(padded(64).len(), 64);
+        assert!(json_body(&route_request(&post("/api/information-dynamics/generate", &padded(64))))["haiku"].is_array());
+        
+        let over = route_request(&post("/api/information-dynamics/generate", &padded(65)));
+        assert_eq!(*over.status(), 413);
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(over.body()).unwrap()["error"]["code"], "payload_too_large");
+        
+        // A declared length over the limit is refused even if less was read
+        let declared = Request::builder()
+            .method(Method::Post)
+            .uri("/api/information-dynamics/generate")
+            .header("content-length", "1000000")
+            .body(b"{}".to_vec())
+            .build();
+        assert_eq!(*route_request(&declared).status(), 413);
+    }
+    
+    #[test]
+    fn ticket_boxes_every_haiku_line_at_its_width() {
+        let mut fortune = sample_fortune();
+        fortune.haiku[2] = "隠れ道の中".to_string();
+        
+        for width in [DEFAULT_TICKET_WIDTH, 28] {
+            let ticket = render_ticket(&fortune, width);
+            let lines: Vec<&str> = ticket.lines().collect();
+            for line in &lines {
+                assert_eq!(display_width(line), width, "{:?}", line);
+                assert!(line.starts_with('|') || line.starts_with('+'));
+            }
+            // Lines too long for a narrow ticket wrap at word boundaries
+            for word in fortune.haiku.iter().flat_map(|line| line.split_whitespace()) {
+                let row = lines.iter().find(|line| line.contains(word)).unwrap();
+                assert!(row.starts_with("| ") && row.ends_with(" |"), "{:?}", row);
+            }
+            if width == DEFAULT_TICKET_WIDTH {
+                assert!(fortune.haiku.iter().all(|haiku_line| lines.iter().any(|line| line.contains(haiku_line.as_str()))));
+            }
+            assert!(lines[lines.len() - 2].contains("2025-08-30"));
+        }
+        
+        set_variable("rare_fortune_chance", "0");
+        let response = route_request(&get("/api/oracle/fortune?format=ticket"));
+        assert!(response.header("content-type").and_then(|value| value.as_str()).is_some_and(|value| value.starts_with("text/plain")));
+        assert!(std::str::from_utf8(response.body()).unwrap().contains("ZELDAR ORACLE"));
+    }
+    
+    #[test]
+    fn discord_webhook_embeds_the_haiku() {
+        let source = MemoryStateSource::from_json(r#"{"haiku_content": "Quantum paths unfold,\nMathematical grace waits—\nInformation near"}"#);
+        let payload = webhook_payload(WebhookFormat::Discord, &sample_metrics(), &source);
+        
+        let embed = &payload["embeds"][0];
+        assert_eq!(embed["description"], "Quantum paths unfold,\nMathematical grace waits—\nInformation near");
+        assert_eq!(embed["fields"][0]["value"], "92.5%");
+        assert_eq!(embed["color"], closure_color(0.9252));
+        assert_eq!(closure_color(0.0), 0x0f3460);
+        assert_eq!(closure_color(1.0), 0xe94560);
+        
+        // Without an Oracle haiku the closure band's template is used
+        let fallback = webhook_payload(WebhookFormat::Discord, &sample_metrics(), &MemoryStateSource::missing());
+        let math = template_pool("en").iter().find(|template| template.theme == "math").unwrap();
+        assert_eq!(fallback["embeds"][0]["description"], math.lines.join("\n"));
+        
+        assert_eq!(webhook_payload(WebhookFormat::Raw, &sample_metrics(), &source), serde_json::json!(sample_metrics()));
+    }
+    
+    #[test]
+    fn duplicated_templates_collapse_to_one() {
+        let template = |theme: &str, lines: [&str; 3]| Template { theme: theme.to_string(), lines: lines.map(str::to_string) };
+        let pool = [
+            template("math", ["Category folds,", "Strange loops embrace paradox—", "Knowing emerges"]),
+            template("desert", ["Loops correlate through", "Mathematical dunes drift—", "Desert sand transforms"]),
+            template("paradox", ["Category folds,", "Strange loops embrace paradox—", "Knowing emerges"]),
+        ];
+        assert_eq!(distinct_template_indices("en", &pool), [0, 1]);
+        
+        let stats = json_body(&route_request(&get("/api/oracle/stats")));
+        assert_eq!(stats["effective_templates"], unique_template_indices("en").len());
+        assert_eq!(stats["effective_mechanisms"], MECHANISMS.len());
+    }
+    
+    #[test]
+    fn template_preview_lists_the_whole_table_with_validation() {
+        let preview = json_body(&route_request(&get("/api/oracle/templates")));
+        let templates = preview["templates"].as_array().unwrap();
+        assert_eq!(preview["count"], SUPPORTED_LANGUAGES.len() * HAIKU_TEMPLATES.len());
+        assert_eq!(templates.len(), SUPPORTED_LANGUAGES.len() * HAIKU_TEMPLATES.len());
+        
+        for template in templates {
+            if template["lang"] == "en" {
+                assert_eq!(template["valid"], true, "{}", template);
+                assert_eq!(template["syllables"], serde_json::json!(HAIKU_SYLLABLES));
+            } else {
+                assert!(template["valid"].is_null() && template["syllables"].is_null());
+            }
+        }
+        
+        let math = json_body(&route_request(&get("/api/oracle/templates?lang=en&theme=math")));
+        assert_eq!(math["count"], 1);
+        assert_eq!(*route_request(&get("/api/oracle/templates?lang=fr")).status(), 400);
+    }
+    
+    /// OpenAPI 3.0 has no type arrays; nullable values say `"nullable": true` instead
+    fn assert_openapi_3_0_types(value: &serde_json::Value, at: &str) {
+        match value {
+            serde_json::Value::Object(object) => {
+                for (key, field) in object {
+                    assert!(key != "type" || !field.is_array(), "{}.type is {}", at, field);
+                    assert_openapi_3_0_types(field, &format!("{}.{}", at, key));
+                }
+            }
+            serde_json::Value::Array(items) => items.iter().for_each(|item| assert_openapi_3_0_types(item, at)),
+            _ => {}
+        }
+    }
+    
+    #[test]
+    fn openapi_types_are_valid_3_0() {
+        assert_openapi_3_0_types(&json_body(&route_request(&get("/api/openapi.json"))), "$");
+    }
+    
+    #[test]
+    fn intensity_chooses_between_standard_and_emergent_pools() {
+        set_variable("rare_fortune_chance", "0");
+        let sources = |query: &str| -> Vec<serde_json::Value> {
+            let bundle = json_body(&route_request(&get(&format!("/api/oracle/fortune?explain=true&count=5&{}", query))));
+            bundle.as_array().unwrap().iter().map(|fortune| fortune["explanation"]["haiku_source"].clone()).collect()
+        };
+        
+        for query in ["intensity=0", "intensity=-20", "intensity=0&seed=3"] {
+            assert!(sources(query).iter().all(|source| source == "standard"), "{}", query);
+        }
+        for query in ["intensity=100", "intensity=250", "intensity=100&seed=3"] {
+            assert!(sources(query).iter().all(|source| source == "template"), "{}", query);
+        }
+        assert_eq!(*route_request(&get("/api/oracle/fortune?intensity=lots")).status(), 400);
+    }
+    
+    #[test]
+    fn maintenance_mode_refuses_everything_but_health() {
+        set_variable("maintenance_mode", "true");
+        
+        for request in [get("/api/oracle/fortune"), get("/"), post("/api/information-dynamics/generate", "{}")] {
+            let response = route_request(&request);
+            assert_eq!(*response.status(), 503);
+            assert!(response.header("retry-after").and_then(|value| value.as_str()).is_some_and(|secs| secs.parse::<u64>().is_ok()));
+            let error = serde_json::from_slice::<serde_json::Value>(response.body()).unwrap()["error"].take();
+            assert_eq!(error["code"], "maintenance");
+        }
+        
+        // Health still answers, with a 503 so load balancers drain the instance
+        let health = route_request(&get("/api/health"));
+        assert_eq!(*health.status(), 503);
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(health.body()).unwrap()["status"], "maintenance");
+        let preflight = Request::builder().method(Method::Options).uri("/api/oracle/fortune").build();
+        assert!((200..300).contains(route_request(&preflight).status()));
+    }
+    
+    #[test]
+    fn nonce_is_echoed_and_bound_to_the_id() {
+        set_variable("rare_fortune_chance", "0");
+        let fortune = |nonce: &str| route_request(&get(&format!("/api/oracle/fortune?seed=5&nonce={}", nonce)));
+        
+        let first = json_body(&fortune("abc123"));
+        assert_eq!(first["request_nonce"], "abc123");
+        let second = json_body(&fortune("xyz789"));
+        assert_eq!(second["haiku"], first["haiku"]);
+        assert_ne!(second["id"], first["id"], "the nonce is part of the id");
+        
+        for invalid in ["has-dash", "sp%20ace", &"a".repeat(MAX_NONCE_LEN + 1)] {
+            assert_eq!(*fortune(invalid).status(), 400, "{}", invalid);
+        }
+        assert_eq!(json_body(&fortune(&"a".repeat(MAX_NONCE_LEN)))["request_nonce"], "a".repeat(MAX_NONCE_LEN));
+    }
+    
+    #[test]
+    fn preflight_advertises_only_the_route_methods() {
+        let allowed = |path: &str| {
+            let response = route_request(&Request::builder().method(Method::Options).uri(path).build());
+            response.header("access-control-allow-methods").and_then(|value| value.as_str()).unwrap().to_string()
+        };
+        
+        assert!(!allowed("/api/version").contains("POST"));
+        assert!(!allowed("/api/oracle/fortune/abc").contains("POST"));
+        assert!(allowed("/api/oracle/fortune").contains("POST"));
+        assert!(!allowed("/api/oracle/contribute").contains("GET"));
+        
+        // A method the route doesn't answer gets a 405 listing the same methods
+        let refused = route_request(&post("/api/version", "{}"));
+        assert_eq!(*refused.status(), 405);
+        assert_eq!(refused.header("allow").and_then(|value| value.as_str()), Some(allowed("/api/version").as_str()));
+    }
+    
+    #[test]
+    fn history_since_returns_only_newer_samples() {
+        let history: VecDeque<MetricsSample> = (1..=6).map(|i| MetricsSample { timestamp: i * 1_000, metrics: sample_metrics() }).collect();
+        Store::open_default().unwrap().set(METRICS_HISTORY_KEY, &serde_json::to_vec(&history).unwrap()).unwrap();
+        let timestamps = |query: &str| -> Vec<u64> {
+            let samples = json_body(&route_request(&get(&format!("/api/information-dynamics/history?{}", query))));
+            samples.as_array().unwrap().iter().map(|sample| sample["timestamp"].as_u64().unwrap()).collect()
+        };
+        
+        assert_eq!(timestamps("since=3000"), [6_000, 5_000, 4_000]);
+        assert_eq!(timestamps("since=3000&limit=2"), [6_000, 5_000]);
+        assert!(timestamps("since=6000").is_empty());
+        assert_eq!(*route_request(&get("/api/information-dynamics/history?since=yesterday")).status(), 400);
+    }
+    
+    #[test]
+    fn templates_file_keeps_valid_entries_and_skips_invalid_ones() {
+        let path = std::env::temp_dir().join(format!("zeldar-templates-{}.json", std::process::id()));
+        std::fs::write(&path, serde_json::to_vec(&serde_json::json!([
+            {"theme": "Emergence", "lines": ["Quantum paths unfold,", "Mathematical grace waits—", "Information near"]},
+            {"theme": "bucles", "lang": "es", "lines": ["Bucles que enlazan", "matemática viva—", "callan las formas"]},
+            {"theme": "broken", "lines": ["Only two lines here", "and nothing after"]},
+        ])).unwrap()).unwrap();
+        set_variable("templates_file", path.to_str().unwrap());
+        
+        let curated = load_templates_file();
+        assert_eq!(curated.values().map(Vec::len).sum::<usize>(), 2);
+        assert_eq!(curated["en"][0].theme, "emergence");
+        assert_eq!(curated["en"][0].lines[2], "Information near");
+        assert_eq!(curated["es"][0].lines[0], "Bucles que enlazan");
+        
+        set_variable("templates_file", path.with_extension("missing").to_str().unwrap());
+        assert!(load_templates_file().is_empty());
+    }
+    
+    #[test]
+    fn correlation_decays_with_the_state_file_age() {
+        let dir = fixture_topos("decay", r#"{"information-dynamics_phi": 3.252, "quantum_entropy": 0.926, "loop_iteration": 42}"#);
+        let correlation = || read_information_dynamics_metrics(&FileStateSource, MetricsMode::File).unwrap().correlation_strength;
+        assert_eq!(correlation(), STATE_CORRELATION);
+        
+        let six_minutes_ago = std::time::SystemTime::now() - Duration::from_secs(360);
+        std::fs::File::options().write(true).open(dir.join("current_loop_state.json")).unwrap().set_modified(six_minutes_ago).unwrap();
+        assert_eq!(correlation(), SIMULATED_CORRELATION_BASELINE);
+        
+        assert_eq!(decayed_correlation(Some(Duration::ZERO)), 0.98);
+        assert!((decayed_correlation(Some(Duration::from_secs(180))) - 0.965).abs() < 1e-9);
+        assert_eq!(decayed_correlation(Some(Duration::from_secs(360))), SIMULATED_CORRELATION_BASELINE);
+        assert_eq!(decayed_correlation(None), SIMULATED_CORRELATION_BASELINE);
+    }
+    
+    #[test]
+    fn batch_runs_each_operation_in_order() {
+        set_variable("metrics_mode", "simulation");
+        set_variable("rare_fortune_chance", "0");
+        let batch = post("/api/batch", r#"[
+            {"method": "GET", "path": "/api/oracle/fortune", "params": {"lang": "en"}},
+            {"method": "GET", "path": "/api/information-dynamics/metrics"},
+            {"method": "DELETE", "path": "/api/information-dynamics/metrics"}
+        ]"#);
+        let results = json_body(&route_request(&batch));
+        let results = results.as_array().unwrap();
+        
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0]["status"], 200);
+        assert_eq!(results[0]["body"]["haiku"].as_array().unwrap().len(), 3);
+        assert_eq!(results[1]["status"], 200);
+        assert!(results[1]["body"]["semantic_closure"].is_f64());
+        assert_eq!(results[2]["status"], 405);
+    }
+    
+    #[test]
+    fn metrics_format_for_people_without_nan() {
+        assert_eq!(fmt_pct(0.885), "88.5%");
+        assert_eq!(fmt_pct(1.0), "100.0%");
+        assert_eq!(fmt_pct(f64::NAN), METRIC_PLACEHOLDER);
+        assert_eq!(fmt_pct(f64::INFINITY), METRIC_PLACEHOLDER);
+        assert_eq!(fmt_coef(0.1 + 0.2), "0.300");
+        assert_eq!(fmt_coef(9.26), "9.260");
+        assert_eq!(fmt_coef(f64::NEG_INFINITY), METRIC_PLACEHOLDER);
+        assert!(!METRIC_PLACEHOLDER.contains("NaN"));
+    }
+    
+    #[test]
+    fn kiosk_mode_adds_the_rotation_at_the_configured_interval() {
+        set_variable("metrics_mode", "simulation");
+        let page = |uri: &str| String::from_utf8(route_request(&get(uri)).into_body()).unwrap();
+        
+        set_variable("kiosk_refresh_secs", "12");
+        let kiosk = page("/?kiosk=true");
+        assert!(kiosk.contains(r#"<meta http-equiv="refresh" content="12">"#));
+        assert!(kiosk.contains(r#"fetch("/api/oracle/reading""#));
+        assert!(kiosk.contains("setInterval(rotate, 12000);"));
+        
+        set_variable("kiosk_refresh_secs", "1");
+        assert!(page("/?kiosk=true").contains("setInterval(rotate, 3000);"));
+        
+        let still = page("/");
+        assert!(!still.contains("http-equiv=\"refresh\""));
+        assert!(!still.contains("setInterval"));
+        assert!(!still.contains("{{kiosk"));
+    }
+    
+    #[test]
+    fn unsupported_methods_get_405_with_allow_and_cors() {
+        let refused = route_request(&Request::builder().method(Method::Put).uri("/api/oracle/fortune").build());
+        assert_eq!(*refused.status(), 405);
+        let header = |name: &str| refused.header(name).and_then(|value| value.as_str()).map(str::to_string);
+        let mut allow: Vec<String> = header("allow").unwrap().split(", ").map(str::to_string).collect();
+        allow.sort();
+        assert_eq!(allow, ["GET", "HEAD", "OPTIONS", "POST"]);
+        assert!(header("access-control-allow-origin").is_some());
+        let body: serde_json::Value = serde_json::from_slice(refused.body()).unwrap();
+        assert_eq!(body["error"]["code"], "method_not_allowed");
+        
+        let unknown = route_request(&Request::builder().method(Method::Put).uri("/api/nowhere").build());
+        assert_eq!(*unknown.status(), 404);
+    }
+    
+    #[test]
+    fn seeded_fortunes_roll_rare_reproducibly() {
+        set_variable("metrics_mode", "simulation");
+        set_variable("rare_fortune_chance", "0.01");
+        let fortune = |seed: u64| json_body(&route_request(&get(&format!("/api/oracle/fortune?seed={}", seed))));
+        
+        // Seed 21 is the first to roll under 1%
+        let rare = fortune(21);
+        assert_eq!(rare["rare"], true);
+        let lines: Vec<&str> = rare["haiku"].as_array().unwrap().iter().map(|line| line.as_str().unwrap()).collect();
+        assert!(RARE_HAIKU.iter().any(|haiku| haiku[..] == lines[..]));
+        assert_eq!(fortune(21)["haiku"], rare["haiku"]);
+        
+        let ordinary = fortune(22);
+        assert!(ordinary.get("rare").is_none());
+        assert!(RARE_HAIKU.iter().all(|haiku| serde_json::json!(haiku) != ordinary["haiku"]));
+    }
+    
+    #[test]
+    fn a_fortune_is_published_once_after_the_response() {
+        set_variable("metrics_mode", "simulation");
+        set_variable("rare_fortune_chance", "0");
+        set_variable("nats_url", "mqtt://127.0.0.1:1883");
+        let fortune = json_body(&route_request(&get("/api/oracle/fortune")));
+        
+        let publisher = MockPublisher::default();
+        publish_pending_fortunes(&publisher);
+        publish_pending_fortunes(&publisher);
+        let published = publisher.published.into_inner();
+        assert_eq!(published.len(), 1);
+        assert_eq!(published[0].len(), 1);
+        let (id, payload) = &published[0][0];
+        assert_eq!(id, fortune["id"].as_str().unwrap());
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(payload).unwrap()["haiku"], fortune["haiku"]);
+    }
+    
+    #[test]
+    fn gift_messages_rotate_but_hold_under_a_seed() {
+        assert_eq!(gift_message(Some(0)), "The loop closes when you give something back.");
+        assert_eq!(gift_message(Some(1)), "This fortune is a gift. Pass one on.");
+        assert_eq!(gift_message(Some(0)), gift_message(Some(0)));
+        
+        set_variable("metrics_mode", "simulation");
+        set_variable("rare_fortune_chance", "0");
+        set_variable("gift_messages", "Carry it forward | | Leave a light on");
+        let reading = json_body(&route_request(&get("/api/oracle/reading?seed=7")));
+        let message = reading["gift_message"].as_str().unwrap().to_string();
+        assert!(["Carry it forward", "Leave a light on"].contains(&message.as_str()));
+        assert_eq!(json_body(&route_request(&get("/api/oracle/reading?seed=7")))["gift_message"], message.as_str());
+        
+        let page = String::from_utf8(route_request(&get("/?seed=7")).into_body()).unwrap();
+        assert!(page.contains(&format!("<p>{}</p>", message)));
+    }
+    
+    #[test]
+    fn phi_maps_linearly_onto_closure() {
+        assert!((phi_to_closure(3.252) - 0.9252).abs() < 1e-12);
+        assert_eq!(phi_to_closure(0.0), 0.6);
+        assert_eq!(phi_to_closure(4.0), 1.0);
+        assert_eq!(phi_to_closure(1_000.0), 1.0);
+        assert_eq!(phi_to_closure(-100.0), 0.0);
+    }
+    
+    #[test]
+    fn responses_report_their_time_in_ms() {
+        for uri in ["/api/health", "/api/nowhere"] {
+            let response = route_timed_request(&get(uri), "req-1".to_string(), Instant::now());
+            let header = |name: &str| response.header(name).and_then(|value| value.as_str()).unwrap().to_string();
+            let elapsed: f64 = header("x-response-time-ms").parse().unwrap();
+            assert!(elapsed.is_finite() && elapsed >= 0.0, "{}", elapsed);
+            assert_eq!(header("x-request-id"), "req-1");
+        }
+        
+        let earlier = Instant::now() - Duration::from_millis(250);
+        assert!(response_time_ms(earlier).parse::<f64>().unwrap() >= 250.0);
+    }
+    
+    #[test]
+    fn compact_metrics_round_trip_through_the_key_table() {
+        let compact = compact_metrics(&sample_metrics()).unwrap();
+        for short in ["sc", "sl", "hc", "sg", "cs", "te"] {
+            assert!(compact.get(short).is_some(), "{}", short);
+        }
+        assert!(compact.get("semantic_closure").is_none());
+        
+        let mut expanded = compact.as_object().unwrap().clone();
+        for (verbose, short) in COMPACT_METRIC_KEYS {
+            if let Some(value) = expanded.remove(*short) {
+                expanded.insert(verbose.to_string(), value);
+            }
+        }
+        assert_eq!(serde_json::Value::Object(expanded), serde_json::to_value(sample_metrics()).unwrap());
+        
+        set_variable("metrics_mode", "simulation");
+        let live = json_body(&route_request(&get("/api/information-dynamics/metrics?compact=true")));
+        assert!(live["sc"].is_f64() && live.get("semantic_closure").is_none());
+        assert!(json_body(&route_request(&get("/api/information-dynamics/metrics")))["semantic_closure"].is_f64());
+    }
+    
+    #[test]
+    fn streams_close_on_maintenance_and_at_their_max_duration() {
+        set_variable("metrics_mode", "simulation");
+        let events = collect_stream_events(0, Instant::now(), || set_variable("maintenance_mode", "true"));
+        assert_eq!(events.len(), 2);
+        assert!(events[0].contains("id: 1\nevent: metrics\n"));
+        assert_eq!(events[1], "event: close\ndata: maintenance\n\n");
+        
+        set_variable("maintenance_mode", "false");
+        set_variable("sse_max_duration_secs", "60");
+        let events = collect_stream_events(0, Instant::now() - Duration::from_secs(61), || panic!("an expired stream must not tick"));
+        assert_eq!(events, ["retry: 2000\n\nevent: close\ndata: max_duration\n\n"]);
+    }
+    
+    #[test]
+    fn contributions_refuse_markup_and_clean_names() {
+        let contribute = |author: &str, last_line: &str| {
+            let body = serde_json::json!({ "lines": ["Quantum paths unfold,", "Mathematical grace waits—", last_line], "author": author });
+            route_request(&post("/api/oracle/contribute", &body.to_string()))
+        };
+        
+        assert_eq!(*contribute("<script>alert(1)</script>", "Information near").status(), 400);
+        assert_eq!(*contribute("Ada", "<b>Information</b> near").status(), 400);
+        
+        let accepted = contribute("  Zoë\u{7} de la\nCruz ", "Information near");
+        assert_eq!(*accepted.status(), 201);
+        let long = contribute(&"Grace Hopper ".repeat(8), "Information near");
+        assert_eq!(*long.status(), 201);
+        
+        let store = Store::open_default().unwrap();
+        let stored = |response: &Response| -> Contribution {
+            let id = json_body(response)["id"].as_str().unwrap().to_string();
+            serde_json::from_slice(&store.get(&format!("{}{}", PENDING_CONTRIBUTION_PREFIX, id)).unwrap().unwrap()).unwrap()
+        };
+        assert_eq!(stored(&accepted).author.as_deref(), Some("Zoë de laCruz"));
+        let long_author = stored(&long).author.unwrap();
+        assert!(long_author.chars().count() <= MAX_AUTHOR_LEN);
+        assert!(!long_author.ends_with(' '));
+    }
+    
+    #[test]
+    fn every_mechanism_has_an_explanation() {
+        for mechanism in MECHANISMS {
+            let explanation = mechanism_explanation(mechanism);
+            assert!(explanation.is_some_and(|text| !text.trim().is_empty()), "{} has no explanation", mechanism);
+        }
+        for (name, _) in MECHANISM_EXPLANATIONS {
+            assert!(MECHANISMS.contains(name), "{} explains no mechanism", name);
+        }
+    }
+    
+    #[test]
+    fn short_links_redirect_to_the_stored_fortune() {
+        set_variable("metrics_mode", "simulation");
+        set_variable("rare_fortune_chance", "0");
+        let fortune = json_body(&route_request(&get("/api/oracle/fortune")));
+        let code = fortune["short_code"].as_str().unwrap();
+        
+        let redirect = route_request(&get(&format!("/f/{}", code)));
+        assert_eq!(*redirect.status(), 302);
+        let location = redirect.header("location").and_then(|value| value.as_str()).unwrap().to_string();
+        assert_eq!(location, format!("/api/oracle/fortune/{}", fortune["id"].as_str().unwrap()));
+        assert_eq!(json_body(&route_request(&get(&location)))["haiku"], fortune["haiku"]);
+        
+        assert_eq!(*route_request(&get("/f/nope")).status(), 404);
+    }
+    
+    #[test]
+    fn fortune_page_fills_placeholders_once() {
+        let mut fortune = sample_fortune();
+        fortune.id = "page-fortune".to_string();
+        fortune.haiku[1] = "Grace waits for {{mechanism}}—".to_string();
+        persist_fortune(&mut fortune);
+        
+        let page = String::from_utf8(route_request(&get("/fortune/page-fortune")).into_body()).unwrap();
+        assert!(page.contains(r#"<meta property="og:title" content="Quantum paths unfold,">"#));
+        for line in ["Quantum paths unfold,", "Grace waits for {{mechanism}}—", "Information near"] {
+            assert!(page.contains(line), "{}", line);
+        }
+        assert_eq!(page.matches(MECHANISMS[0]).count(), 1);
+        
+        assert_eq!(fill_placeholders("{{a}}{{b}} {{c}} {{", &[("a", "{{b}}"), ("b", "2")]), "{{b}}2 {{c}} {{");
+    }
+}